@@ -38,7 +38,7 @@ RUNNERS=(
     "cargo_asan --release"
 )
 TESTS=(
-    "--doc arc"
+    "--doc src/arc.rs"
     "--test arc"
 )
 arc_basic_failed=false
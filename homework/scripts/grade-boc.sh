@@ -68,4 +68,18 @@ if [ "$stress_test_failed" = false ]; then
     SCORE=$((SCORE + 40))
 fi
 
+echo "3. Thread pool runtime tests (not graded, need hello_server::ThreadPool)"
+
+TESTS=(
+    "--test boc thread_pool_runtime"
+)
+
+RUNNER="cargo"
+echo "Running with $RUNNER, timeout $TIMEOUT..."
+if [ $(run_tests) -ne 0 ]; then
+    echo "Thread pool runtime tests failed."
+else
+    echo "Thread pool runtime tests passed."
+fi
+
 echo "Score: $SCORE / 100"
@@ -13,7 +13,7 @@ export RUST_TEST_THREADS=1
 
 TEMPLATE_REV=HEAD
 check_diff ./src/hazard_pointer/hazard.rs 89
-check_diff ./src/hazard_pointer/retire.rs 27
+check_diff ./src/hazard_pointer/retire.rs 123
 
 lines=$(grep_skip_comment transmute "$BASEDIR"/../src/hazard_pointer/{retire,hazard}.rs)
 if [ -n "$lines" ]; then
@@ -66,9 +66,12 @@ for RUNNER in "${RUNNERS[@]}"; do
     fi
 
     if [ "$retire_failed" = false ]; then
-        echo "Running a test in retire.rs with $RUNNER..."
+        echo "Running tests in retire.rs with $RUNNER..."
         TESTS=(
             "--lib -- --exact hazard_pointer::retire::tests::retire_threshold_collect"
+            "--lib -- --exact hazard_pointer::retire::tests::retire_with_deleter"
+            "--lib -- --exact hazard_pointer::retire::tests::stats"
+            "--lib -- --exact hazard_pointer::retire::tests::orphan_adopted"
         )
         if [ $(run_tests) -ne 0 ]; then
             retire_failed=true
@@ -1,4 +1,7 @@
-use crossbeam_epoch::Guard;
+use crossbeam_epoch::{Guard, Owned};
+use cs431::lockfree::SmrList;
+use cs431::lockfree::list::{Cursor, List, Node};
+use cs431::lockfree::smr::Smr;
 
 /// Trait for a concurrent key-value map.
 pub trait ConcurrentMap<K: ?Sized, V> {
@@ -13,6 +16,187 @@ pub trait ConcurrentMap<K: ?Sized, V> {
     /// Unlike stack or queue's pop that can return `Option<V>`, since a `delete`d
     /// value may also be `lookup`ed, we can only return a reference, not full ownership.
     fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, ()>;
+
+    /// Lookups the given key, inserting the value computed by `f` if the key is absent. Returns the
+    /// reference to the value found or inserted.
+    ///
+    /// The default implementation is a sequence of `lookup` and `insert`, so `f` may be called
+    /// again if the inserted value is deleted before it is looked up. Then the value returned may
+    /// not be the one inserted, so maps should override it to return the inserted value.
+    fn get_or_insert_with<'a, F>(&'a self, key: K, mut f: F, guard: &'a Guard) -> &'a V
+    where
+        K: Sized + Clone,
+        F: FnMut() -> V,
+    {
+        let mut value = None;
+        loop {
+            if let Some(v) = self.lookup(&key, guard) {
+                return v;
+            }
+            match self.insert(key.clone(), value.take().unwrap_or_else(&mut f), guard) {
+                Ok(()) => {
+                    if let Some(v) = self.lookup(&key, guard) {
+                        return v;
+                    }
+                }
+                Err(v) => value = Some(v),
+            }
+        }
+    }
+
+    /// Inserts the value `f(None)` if the key is absent, or replaces the value `v` with
+    /// `f(Some(v))` otherwise. Returns the reference to the replaced value.
+    ///
+    /// `f` may be called more than once when there are concurrent updates. The default
+    /// implementation is a sequence of `delete` and `insert`, so the key may be absent in between.
+    fn upsert<'a, F>(&'a self, key: K, mut f: F, guard: &'a Guard) -> Option<&'a V>
+    where
+        K: Sized + Clone,
+        F: FnMut(Option<&V>) -> V,
+    {
+        loop {
+            let old = self.delete(&key, guard).ok();
+            if self.insert(key.clone(), f(old), guard).is_ok() {
+                return old;
+            }
+        }
+    }
+
+    /// Replaces the value of the given key with `new` if it is equal to `expected`. Returns the
+    /// reference to the replaced value, or `new` back if the key is absent or the value is not
+    /// equal to `expected`.
+    ///
+    /// The default implementation is a sequence of `lookup`, `delete` and `insert`, so it is not
+    /// atomic: the key is absent in between, and if the value is replaced concurrently after the
+    /// `lookup`, the new value is deleted and lost, as a deleted value can't be put back. Maps
+    /// should override it to compare and replace the value atomically.
+    fn compare_and_replace<'a>(
+        &'a self,
+        key: &K,
+        expected: &V,
+        new: V,
+        guard: &'a Guard,
+    ) -> Result<&'a V, V>
+    where
+        K: Sized + Clone,
+        V: PartialEq,
+    {
+        if self.lookup(key, guard) != Some(expected) {
+            return Err(new);
+        }
+        match self.delete(key, guard) {
+            Ok(old) if old == expected => self.insert(key.clone(), new, guard).map(|()| old),
+            _ => Err(new),
+        }
+    }
+}
+
+/// Finds the key in the list with the Harris strategy, retrying until the search succeeds.
+fn find<'g, K: Ord, V>(
+    list: &'g List<K, V>,
+    key: &K,
+    guard: &'g Guard,
+) -> (bool, Cursor<'g, K, V>) {
+    loop {
+        let mut cursor = list.head(guard);
+        if let Ok(found) = cursor.find_harris(key, guard) {
+            return (found, cursor);
+        }
+    }
+}
+
+// The lock-free list is a map, where the updates are made atomic with `Cursor::replace`.
+impl<K: Ord, V> ConcurrentMap<K, V> for List<K, V> {
+    fn lookup<'a>(&'a self, key: &K, guard: &'a Guard) -> Option<&'a V> {
+        self.harris_lookup(key, guard)
+    }
+
+    fn insert(&self, key: K, value: V, guard: &Guard) -> Result<(), V> {
+        let mut node = Owned::new(Node::new(key, value));
+        loop {
+            let (found, mut cursor) = find(self, node.key(), guard);
+            if found {
+                return Err(node.into_box().into_value());
+            }
+            match cursor.insert(node, guard) {
+                Ok(()) => return Ok(()),
+                Err(n) => node = n,
+            }
+        }
+    }
+
+    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, ()> {
+        self.harris_delete(key, guard).ok_or(())
+    }
+
+    fn get_or_insert_with<'a, F>(&'a self, key: K, mut f: F, guard: &'a Guard) -> &'a V
+    where
+        K: Clone,
+        F: FnMut() -> V,
+    {
+        let (found, cursor) = find(self, &key, guard);
+        if found {
+            return cursor.lookup();
+        }
+
+        let mut node = Owned::new(Node::new(key, f()));
+        loop {
+            let (found, mut cursor) = find(self, node.key(), guard);
+            if found {
+                return cursor.lookup();
+            }
+            match cursor.insert(node, guard) {
+                Ok(()) => return cursor.lookup(),
+                Err(n) => node = n,
+            }
+        }
+    }
+
+    fn upsert<'a, F>(&'a self, key: K, mut f: F, guard: &'a Guard) -> Option<&'a V>
+    where
+        K: Clone,
+        F: FnMut(Option<&V>) -> V,
+    {
+        let (mut found, mut cursor) = find(self, &key, guard);
+        let mut node = Owned::new(Node::new(key, f(found.then(|| cursor.lookup()))));
+        loop {
+            let result = if found {
+                cursor.replace(node, guard).map(Some)
+            } else {
+                cursor.insert(node, guard).map(|()| None)
+            };
+            match result {
+                Ok(old) => return old,
+                Err(n) => node = n,
+            }
+
+            (found, cursor) = find(self, node.key(), guard);
+            *node.value_mut() = f(found.then(|| cursor.lookup()));
+        }
+    }
+
+    fn compare_and_replace<'a>(
+        &'a self,
+        key: &K,
+        expected: &V,
+        mut new: V,
+        guard: &'a Guard,
+    ) -> Result<&'a V, V>
+    where
+        K: Clone,
+        V: PartialEq,
+    {
+        loop {
+            let (found, mut cursor) = find(self, key, guard);
+            if !found || cursor.lookup() != expected {
+                return Err(new);
+            }
+            match cursor.replace(Owned::new(Node::new(key.clone(), new)), guard) {
+                Ok(old) => return Ok(old),
+                Err(n) => new = n.into_box().into_value(),
+            }
+        }
+    }
 }
 
 /// Trait for a concurrent set.
@@ -26,3 +210,18 @@ pub trait ConcurrentSet<T> {
     /// Removes the value from the set. Returns whether the value was present in the set.
     fn remove(&self, value: &T) -> bool;
 }
+
+// A list with unit values is a set, so that it can be tested with any reclamation scheme.
+impl<T: Ord, R: Smr> ConcurrentSet<T> for SmrList<T, (), R> {
+    fn contains(&self, value: &T) -> bool {
+        SmrList::contains(self, value)
+    }
+
+    fn insert(&self, value: T) -> bool {
+        SmrList::insert(self, value, ())
+    }
+
+    fn remove(&self, value: &T) -> bool {
+        SmrList::remove(self, value)
+    }
+}
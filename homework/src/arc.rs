@@ -2,15 +2,17 @@
 //!
 //! See the [`Arc<T>`][Arc] documentation for more details.
 
+use std::alloc::{self, Layout};
 use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
 use std::ops::Deref;
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
 #[cfg(not(feature = "check-loom"))]
-use std::sync::atomic::{AtomicUsize, Ordering, fence};
-use std::{fmt, mem};
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering, fence};
+use std::{fmt, hint, mem};
 
 #[cfg(feature = "check-loom")]
-use loom::sync::atomic::{AtomicUsize, Ordering, fence};
+use loom::sync::atomic::{AtomicPtr, AtomicUsize, Ordering, fence};
 
 const MAX_REFCOUNT: usize = (isize::MAX) as usize;
 
@@ -152,44 +154,127 @@ const MAX_REFCOUNT: usize = (isize::MAX) as usize;
 /// counting in general.
 ///
 /// [rc_examples]: std::rc#examples
-pub struct Arc<T> {
+pub struct Arc<T: ?Sized> {
     ptr: NonNull<ArcInner<T>>,
     phantom: PhantomData<ArcInner<T>>,
 }
 
-unsafe impl<T: Sync + Send> Send for Arc<T> {}
-unsafe impl<T: Sync + Send> Sync for Arc<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Send for Arc<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for Arc<T> {}
 
-impl<T> Arc<T> {
+impl<T: ?Sized> Arc<T> {
     fn from_inner(ptr: NonNull<ArcInner<T>>) -> Self {
         Self {
             ptr,
             phantom: PhantomData,
         }
     }
-}
 
-struct ArcInner<T> {
-    count: AtomicUsize,
-    data: T,
-}
+    /// Consumes the `Arc` without decrementing the count, and returns the pointer to the inner.
+    fn into_inner_ptr(this: Self) -> NonNull<ArcInner<T>> {
+        let ptr = this.ptr;
+        mem::forget(this);
+        ptr
+    }
 
-unsafe impl<T: Sync + Send> Send for ArcInner<T> {}
-unsafe impl<T: Sync + Send> Sync for ArcInner<T> {}
+    /// Allocates an `ArcInner<T>` whose data has `value_layout`, and initializes the counts to
+    /// one. The data is left uninitialized.
+    ///
+    /// `mem_to_inner` should attach the metadata of the data, e.g. the length of a slice, to the
+    /// allocated memory block.
+    fn allocate_for_layout(
+        value_layout: Layout,
+        mem_to_inner: impl FnOnce(*mut u8) -> *mut ArcInner<T>,
+    ) -> NonNull<ArcInner<T>> {
+        // This is the layout of `ArcInner<T>` as it's `repr(C)`. It's never zero-sized.
+        let layout = Layout::new::<ArcInner<()>>()
+            .extend(value_layout)
+            .expect("too large data")
+            .0
+            .pad_to_align();
+        // SAFETY: `layout` is not zero-sized.
+        let mem = unsafe { alloc::alloc(layout) };
+        if mem.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
 
-impl<T> Arc<T> {
-    /// Constructs a new `Arc<T>`.
-    #[inline]
-    pub fn new(data: T) -> Arc<T> {
-        let x = Box::new(ArcInner {
-            count: AtomicUsize::new(1),
-            data,
+        let inner = mem_to_inner(mem);
+        // SAFETY: `inner` is a valid pointer to the allocated memory block.
+        unsafe {
+            (&raw mut (*inner).count).write(AtomicUsize::new(1));
+            (&raw mut (*inner).weak).write(AtomicUsize::new(1));
+            NonNull::new_unchecked(inner)
+        }
+    }
+
+    /// Moves the value in the `Box` to a new `Arc`.
+    ///
+    /// `Arc<T>` can't be coerced to `Arc<dyn Trait>` since `CoerceUnsized` is unstable. Instead,
+    /// coerce the `Box` before converting it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fmt::Display;
+    ///
+    /// use cs431_homework::Arc;
+    ///
+    /// let x: Arc<dyn Display> = Arc::from_box(Box::new(5));
+    /// assert_eq!(x.to_string(), "5");
+    /// ```
+    pub fn from_box(b: Box<T>) -> Arc<T> {
+        let value_layout = Layout::for_value(&*b);
+        let bptr = Box::into_raw(b);
+        let inner = Self::allocate_for_layout(value_layout, |mem| {
+            // Replace the address of the fat pointer `bptr` with `mem`, keeping the metadata. The
+            // address is the first word of a fat pointer.
+            let mut inner = bptr as *mut ArcInner<T>;
+            // SAFETY: `inner` is at least one word.
+            unsafe { (&raw mut inner).cast::<*mut u8>().write(mem) };
+            inner
         });
-        Self::from_inner(Box::leak(x).into())
+
+        // SAFETY: `inner` is allocated for a value of `value_layout`, and we free the `Box` without
+        // dropping the moved value.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                bptr as *const u8,
+                (&raw mut (*inner.as_ptr()).data).cast::<u8>(),
+                value_layout.size(),
+            );
+            drop(Box::from_raw(bptr as *mut ManuallyDrop<T>));
+        }
+        Self::from_inner(inner)
     }
+}
 
+impl<T> Arc<[T]> {
+    /// Allocates an `ArcInner<[T]>` of length `len`, with the elements uninitialized.
+    fn allocate_for_slice(len: usize) -> NonNull<ArcInner<[T]>> {
+        Self::allocate_for_layout(Layout::array::<T>(len).expect("too large data"), |mem| {
+            ptr::slice_from_raw_parts_mut(mem.cast::<T>(), len) as *mut ArcInner<[T]>
+        })
+    }
+}
+
+// `repr(C)` so that the layout of `ArcInner<T>` for unsized `T` can be computed from the layout of
+// the data. See `allocate_for_layout`.
+#[repr(C)]
+struct ArcInner<T: ?Sized> {
+    count: AtomicUsize,
+    /// The number of `Weak`s, plus one if there are any `Arc`s. All the `Arc`s together hold one
+    /// weak reference, which is dropped after the data is dropped.
+    weak: AtomicUsize,
+    /// Dropped by the last `Arc`, while the memory block is kept until the last `Weak` is dropped.
+    data: ManuallyDrop<T>,
+}
+
+unsafe impl<T: ?Sized + Sync + Send> Send for ArcInner<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for ArcInner<T> {}
+
+impl<T: ?Sized> Arc<T> {
     /// Returns a mutable reference into the given `Arc` if there are
-    /// no other `Arc`. Otherwise, return `None`.
+    /// no other `Arc` or `Weak` to the same allocation. Otherwise, return `None`.
     ///
     /// # Examples
     ///
@@ -205,6 +290,12 @@ impl<T> Arc<T> {
     ///
     /// drop(y);
     /// assert!(Arc::get_mut(&mut x).is_some());
+    ///
+    /// let w = Arc::downgrade(&x);
+    /// assert!(Arc::get_mut(&mut x).is_none());
+    ///
+    /// drop(w);
+    /// assert!(Arc::get_mut(&mut x).is_some());
     /// ```
     #[inline]
     pub fn get_mut(this: &mut Self) -> Option<&mut T> {
@@ -213,6 +304,10 @@ impl<T> Arc<T> {
 
     // Used in `get_mut` and `make_mut` to check if the given `Arc` is the unique reference to the
     // underlying data.
+    //
+    // There should be no `Weak`s either, since they may be upgraded to `Arc`s at any time. To
+    // prevent a concurrent `downgrade` while checking the count, "lock" the weak count by setting
+    // it to `usize::MAX` (see `downgrade`).
     #[inline]
     fn is_unique(&mut self) -> bool {
         todo!()
@@ -296,13 +391,114 @@ impl<T> Arc<T> {
     /// ```
     #[inline]
     pub fn ptr_eq(this: &Self, other: &Self) -> bool {
-        this.ptr.as_ptr() == other.ptr.as_ptr()
+        ptr::addr_eq(this.ptr.as_ptr(), other.ptr.as_ptr())
+    }
+
+    /// Drops the data, and then the weak reference of the `Arc`s. Call this in `drop` when the
+    /// last `Arc` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// The count must have dropped to zero by `self`, and the accesses to the data by the other
+    /// `Arc`s must happen before this.
+    unsafe fn drop_slow(&mut self) {
+        // SAFETY: No other `Arc`s are left, and the `Weak`s never access the data.
+        unsafe { ManuallyDrop::drop(&mut (*self.ptr.as_ptr()).data) };
+        drop(Weak { ptr: self.ptr });
+    }
+
+    /// Creates a new `Weak` pointer to this allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let five = Arc::new(5);
+    /// let weak_five = Arc::downgrade(&five);
+    /// assert_eq!(*weak_five.upgrade().unwrap(), 5);
+    /// ```
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        let mut cur = this.inner().weak.load(Ordering::Relaxed);
+        loop {
+            // The weak count is locked by `is_unique`. Wait until it's unlocked.
+            if cur == usize::MAX {
+                hint::spin_loop();
+                cur = this.inner().weak.load(Ordering::Relaxed);
+                continue;
+            }
+            assert!(cur <= MAX_REFCOUNT, "too many `Weak`s");
+
+            // Acquire synchronizes with the release in `is_unique`, so that the writes through
+            // the `&mut T` from `get_mut` happen before the later accesses through `upgrade`.
+            match this.inner().weak.compare_exchange_weak(
+                cur,
+                cur + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Weak { ptr: this.ptr },
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    /// Gets the number of `Weak`s to this allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let five = Arc::new(5);
+    /// let _weak_five = Arc::downgrade(&five);
+    /// assert_eq!(1, Arc::weak_count(&five));
+    /// ```
+    #[inline]
+    pub fn weak_count(this: &Self) -> usize {
+        let count = this.inner().weak.load(Ordering::Acquire);
+        // If the weak count is locked, there were no `Weak`s when it was locked.
+        if count == usize::MAX { 0 } else { count - 1 }
+    }
+}
+
+impl<T> Arc<T> {
+    /// Constructs a new `Arc<T>`.
+    #[inline]
+    pub fn new(data: T) -> Arc<T> {
+        let x = Box::new(ArcInner {
+            count: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+            data: ManuallyDrop::new(data),
+        });
+        Self::from_inner(Box::leak(x).into())
+    }
+
+    /// Constructs a new `Arc` with uninitialized contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let mut five = Arc::<u32>::new_uninit();
+    /// // Deferred initialization:
+    /// Arc::get_mut(&mut five).unwrap().write(5);
+    /// let five = unsafe { five.assume_init() };
+    /// assert_eq!(*five, 5);
+    /// ```
+    #[inline]
+    pub fn new_uninit() -> Arc<MaybeUninit<T>> {
+        Arc::new(MaybeUninit::uninit())
     }
 
     /// Returns the inner value, if the given `Arc` is unique.
     ///
     /// Otherwise, an `Err` is returned with the same `Arc` that was passed in.
     ///
+    /// This succeeds even if there are `Weak`s, which then fail to upgrade. As in `drop_slow`, the
+    /// weak reference of the `Arc`s should be dropped after the data is moved out.
+    ///
     /// # Examples
     ///
     /// ```
@@ -321,10 +517,23 @@ impl<T> Arc<T> {
     }
 }
 
+impl<T> Arc<MaybeUninit<T>> {
+    /// Converts to `Arc<T>`.
+    ///
+    /// # Safety
+    ///
+    /// The inner value must be initialized.
+    #[inline]
+    pub unsafe fn assume_init(self) -> Arc<T> {
+        // `MaybeUninit<T>` has the same layout as `T`, and so do `ArcInner`s as they are `repr(C)`.
+        Arc::from_inner(Arc::into_inner_ptr(self).cast())
+    }
+}
+
 impl<T: Clone> Arc<T> {
     /// Makes a mutable reference into the given `Arc`.
     ///
-    /// If there are other `Arc` to the same allocation, then `make_mut` will create a new
+    /// If there are other `Arc` or `Weak` to the same allocation, then `make_mut` will create a new
     /// allocation and invoke `clone` on the inner value to ensure unique ownership. This is also
     /// referred to as clone-on-write.
     ///
@@ -353,7 +562,7 @@ impl<T: Clone> Arc<T> {
     }
 }
 
-impl<T> Clone for Arc<T> {
+impl<T: ?Sized> Clone for Arc<T> {
     /// Makes a clone of the `Arc` pointer.
     ///
     /// This creates another pointer to the same allocation, increasing the
@@ -378,7 +587,7 @@ impl<T> Clone for Arc<T> {
     }
 }
 
-impl<T> Deref for Arc<T> {
+impl<T: ?Sized> Deref for Arc<T> {
     type Target = T;
 
     #[inline]
@@ -387,11 +596,11 @@ impl<T> Deref for Arc<T> {
     }
 }
 
-impl<T> Drop for Arc<T> {
+impl<T: ?Sized> Drop for Arc<T> {
     /// Drops the `Arc`.
     ///
     /// This will decrement the reference count. If the reference
-    /// count reaches zero, we `drop` the inner value.
+    /// count reaches zero, we `drop` the inner value with `drop_slow`.
     ///
     /// # Examples
     ///
@@ -417,20 +626,321 @@ impl<T> Drop for Arc<T> {
     }
 }
 
-impl<T: fmt::Display> fmt::Display for Arc<T> {
+impl<T: ?Sized + fmt::Display> fmt::Display for Arc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Display::fmt(&**self, f)
     }
 }
 
-impl<T: fmt::Debug> fmt::Debug for Arc<T> {
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Arc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Debug::fmt(&**self, f)
     }
 }
 
-impl<T> fmt::Pointer for Arc<T> {
+impl<T: ?Sized> fmt::Pointer for Arc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&(&**self), f)
     }
 }
+
+impl<T: ?Sized> From<Box<T>> for Arc<T> {
+    /// Moves the value in the `Box` to a new `Arc`. See [`Arc::from_box`].
+    #[inline]
+    fn from(b: Box<T>) -> Arc<T> {
+        Arc::from_box(b)
+    }
+}
+
+impl<T> From<Vec<T>> for Arc<[T]> {
+    /// Moves the elements of the `Vec` to a new `Arc<[T]>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let shared: Arc<[i32]> = Arc::from(vec![1, 2, 3]);
+    /// assert_eq!(&shared[..], [1, 2, 3]);
+    /// ```
+    fn from(mut v: Vec<T>) -> Arc<[T]> {
+        let inner = Arc::allocate_for_slice(v.len());
+        // SAFETY: `inner` is allocated for `v.len()` elements. The elements are moved out of `v`,
+        // so `v` should free its buffer without dropping them.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                v.as_ptr(),
+                (&raw mut (*inner.as_ptr()).data).cast::<T>(),
+                v.len(),
+            );
+            v.set_len(0);
+        }
+        Arc::from_inner(inner)
+    }
+}
+
+impl<T: Clone> From<&[T]> for Arc<[T]> {
+    /// Clones the elements of the slice to a new `Arc<[T]>`.
+    #[inline]
+    fn from(v: &[T]) -> Arc<[T]> {
+        Arc::from(v.to_vec())
+    }
+}
+
+impl From<&str> for Arc<str> {
+    /// Copies the string slice to a new `Arc<str>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let shared: Arc<str> = Arc::from("eggplant");
+    /// assert_eq!("eggplant", &shared[..]);
+    /// ```
+    fn from(v: &str) -> Arc<str> {
+        let bytes = Arc::<[u8]>::from(v.as_bytes());
+        // SAFETY: `str` has the same layout as `[u8]`, and the bytes are valid UTF-8.
+        Arc::from_inner(unsafe {
+            NonNull::new_unchecked(Arc::into_inner_ptr(bytes).as_ptr() as *mut ArcInner<str>)
+        })
+    }
+}
+
+impl From<String> for Arc<str> {
+    /// Copies the string to a new `Arc<str>`.
+    #[inline]
+    fn from(v: String) -> Arc<str> {
+        Arc::from(&v[..])
+    }
+}
+
+/// A non-owning reference to the allocation of an [`Arc`], created by [`Arc::downgrade`].
+///
+/// A `Weak` keeps the allocation alive but not the data, and can be upgraded to an `Arc` only while
+/// there are other `Arc`s. Use it to break cycles of `Arc`s, e.g. the pointers from children to
+/// their parent in a tree.
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::Arc;
+///
+/// let strong = Arc::new(5);
+/// let weak = Arc::downgrade(&strong);
+/// assert_eq!(*weak.upgrade().unwrap(), 5);
+///
+/// drop(strong);
+/// assert!(weak.upgrade().is_none());
+/// ```
+pub struct Weak<T: ?Sized> {
+    ptr: NonNull<ArcInner<T>>,
+}
+
+unsafe impl<T: ?Sized + Sync + Send> Send for Weak<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for Weak<T> {}
+
+impl<T: ?Sized> Weak<T> {
+    // The data may have been dropped, so we don't create a reference to the whole `ArcInner`.
+    #[inline]
+    fn count(&self) -> &AtomicUsize {
+        // SAFETY: The memory block is alive while this `Weak` is alive.
+        unsafe { &(*self.ptr.as_ptr()).count }
+    }
+
+    #[inline]
+    fn weak(&self) -> &AtomicUsize {
+        // SAFETY: The memory block is alive while this `Weak` is alive.
+        unsafe { &(*self.ptr.as_ptr()).weak }
+    }
+
+    /// Attempts to upgrade the `Weak` to an `Arc`.
+    ///
+    /// Returns `None` if the data has been dropped, i.e. there are no `Arc`s to the allocation.
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        let mut count = self.count().load(Ordering::Relaxed);
+        loop {
+            // Once the count drops to zero, the data is (being) dropped. Never increment it back.
+            if count == 0 {
+                return None;
+            }
+            assert!(count <= MAX_REFCOUNT, "too many `Arc`s");
+
+            // The `Weak` is created after the data is initialized, so Relaxed would suffice as in
+            // `clone`. We use Acquire to be on the safe side, as in the standard library.
+            match self.count().compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(Arc::from_inner(self.ptr)),
+                Err(old) => count = old,
+            }
+        }
+    }
+
+    /// Gets the number of `Arc`s to this allocation.
+    #[inline]
+    pub fn strong_count(&self) -> usize {
+        self.count().load(Ordering::Acquire)
+    }
+}
+
+impl<T: ?Sized> Clone for Weak<T> {
+    /// Makes a clone of the `Weak` pointer, increasing the weak count.
+    #[inline]
+    fn clone(&self) -> Weak<T> {
+        // As in `Arc::clone`, we already have a weak reference, so the memory block is alive.
+        if self.weak().fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+            panic!("too many `Weak`s");
+        }
+        Weak { ptr: self.ptr }
+    }
+}
+
+impl<T: ?Sized> Drop for Weak<T> {
+    /// Drops the `Weak`, and deallocates the memory block if it's the last weak reference.
+    fn drop(&mut self) {
+        if self.weak().fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        fence(Ordering::Acquire);
+        // SAFETY: There are no `Arc`s and `Weak`s left, and the data is already dropped. Dropping
+        // the `Box` frees the memory block without dropping the data again as it's `ManuallyDrop`.
+        drop(unsafe { Box::from_raw(self.ptr.as_ptr()) });
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
+
+/// An [`Arc`] that can be loaded and replaced atomically.
+///
+/// The `AtomicArc` owns a reference to its current allocation. A `load` reads the pointer and then
+/// increments the count, so the reference of the `AtomicArc` should not be dropped in between.
+/// Hence, when an allocation is replaced, the decrement for its reference is deferred with
+/// `crossbeam_epoch` until the concurrent `load`s are done.
+///
+/// So the count of a replaced allocation includes the reference of the `AtomicArc` for a while,
+/// until the epoch advances. Until then, [`Arc::count`] is larger than the number of `Arc`s, and
+/// [`Arc::get_mut`], [`Arc::make_mut`] and [`Arc::try_unwrap`] treat the allocation as shared, e.g.
+/// `try_unwrap` on the `Arc` returned by `swap` may fail. The decrement can't be made eager
+/// instead, as then the other `Arc`s may free the allocation while a `load` is incrementing it.
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::{Arc, AtomicArc};
+///
+/// let config = AtomicArc::new(Arc::new(1));
+/// let old = config.load();
+/// config.store(Arc::new(2));
+/// assert_eq!(*old, 1);
+/// assert_eq!(*config.load(), 2);
+/// ```
+pub struct AtomicArc<T> {
+    ptr: AtomicPtr<ArcInner<T>>,
+    phantom: PhantomData<Arc<T>>,
+}
+
+unsafe impl<T: Sync + Send> Send for AtomicArc<T> {}
+unsafe impl<T: Sync + Send> Sync for AtomicArc<T> {}
+
+impl<T: Sync + Send + 'static> AtomicArc<T> {
+    /// Constructs a new `AtomicArc<T>` with the given `Arc`.
+    pub fn new(arc: Arc<T>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Arc::into_inner_ptr(arc).as_ptr()),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Loads the current `Arc`.
+    pub fn load(&self) -> Arc<T> {
+        let _guard = crossbeam_epoch::pin();
+        let ptr = self.ptr.load(Ordering::Acquire);
+        // SAFETY: `ptr` is never null. The reference of `self` to `ptr` is dropped only after
+        // `_guard` is unpinned, so the count is not zero and we can increment it.
+        let arc = ManuallyDrop::new(Arc::from_inner(unsafe { NonNull::new_unchecked(ptr) }));
+        Arc::clone(&arc)
+    }
+
+    /// Stores the given `Arc`.
+    pub fn store(&self, new: Arc<T>) {
+        let guard = crossbeam_epoch::pin();
+        let old = self
+            .ptr
+            .swap(Arc::into_inner_ptr(new).as_ptr(), Ordering::AcqRel);
+        // SAFETY: `old` is never null, and we own the reference of `self` to it.
+        let old = Arc::from_inner(unsafe { NonNull::new_unchecked(old) });
+        guard.defer(move || drop(old));
+    }
+
+    /// Stores the given `Arc`, and returns the previous one.
+    pub fn swap(&self, new: Arc<T>) -> Arc<T> {
+        let guard = crossbeam_epoch::pin();
+        let old = self
+            .ptr
+            .swap(Arc::into_inner_ptr(new).as_ptr(), Ordering::AcqRel);
+        // SAFETY: `old` is never null, and we own the reference of `self` to it.
+        let old = Arc::from_inner(unsafe { NonNull::new_unchecked(old) });
+        let result = Arc::clone(&old);
+        guard.defer(move || drop(old));
+        result
+    }
+
+    /// Stores `new` if the current `Arc` points to the same allocation as `current`.
+    ///
+    /// Returns the previous `Arc` on success. Otherwise, returns `new` back.
+    ///
+    /// As `current` keeps its allocation alive, the allocation can't be freed and reused for
+    /// another `Arc` in the meantime, so comparing the pointers is free from the ABA problem.
+    pub fn compare_exchange(&self, current: &Arc<T>, new: Arc<T>) -> Result<Arc<T>, Arc<T>> {
+        let guard = crossbeam_epoch::pin();
+        match self.ptr.compare_exchange(
+            current.ptr.as_ptr(),
+            new.ptr.as_ptr(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(old) => {
+                let _ = Arc::into_inner_ptr(new);
+                // SAFETY: `old` is never null, and we own the reference of `self` to it.
+                let old = Arc::from_inner(unsafe { NonNull::new_unchecked(old) });
+                let result = Arc::clone(&old);
+                guard.defer(move || drop(old));
+                Ok(result)
+            }
+            Err(_) => Err(new),
+        }
+    }
+
+    /// Consumes the `AtomicArc`, and returns the current `Arc`.
+    pub fn into_inner(self) -> Arc<T> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: The pointer is never null, and we own the reference of `self` to it.
+        Arc::from_inner(unsafe { NonNull::new_unchecked(this.ptr.load(Ordering::Relaxed)) })
+    }
+}
+
+impl<T> Drop for AtomicArc<T> {
+    fn drop(&mut self) {
+        // SAFETY: The pointer is never null, and we own the reference of `self` to it. No `load`s
+        // are in progress since we have `&mut self`.
+        drop(Arc::from_inner(unsafe {
+            NonNull::new_unchecked(self.ptr.load(Ordering::Relaxed))
+        }));
+    }
+}
+
+impl<T> fmt::Debug for AtomicArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicArc")
+            .field(&self.ptr.load(Ordering::Relaxed))
+            .finish()
+    }
+}
@@ -0,0 +1,561 @@
+//! Biased reference-counting pointers.
+//!
+//! Choi, Shull, and Torrellas. Biased Reference Counting: Minimizing Atomic Operations in Garbage
+//! Collection. PACT 2018.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicIsize, AtomicPtr, AtomicUsize, Ordering, fence};
+use std::{fmt, ptr};
+
+const MAX_REFCOUNT: isize = isize::MAX / 16;
+
+/// The lowest bit of `shared`, set when the biased count is merged into the shared count.
+const MERGED: isize = 1;
+
+/// The second lowest bit of `shared`, set when the object is queued to be merged by the owner.
+const QUEUED: isize = 2;
+
+/// One reference in `shared`.
+const ONE: isize = 4;
+
+/// An [`Arc`](crate::Arc) whose count is biased towards the thread that created it.
+///
+/// Most objects are cloned and dropped only by the thread that created them, called the owner. So
+/// the count is split into two: the owner updates the biased count with plain loads and stores,
+/// and the other threads update the shared count with atomic read-modify-write operations. The
+/// shared count may become negative, e.g. when a clone made by the owner is dropped by another
+/// thread.
+///
+/// The object is freed when the sum of the two counts drops to zero, which can be known only after
+/// the biased count is merged into the shared count. The owner merges it when the biased count
+/// drops to zero, or when the owner exits. If the clones made by the owner are dropped by other
+/// threads, e.g. when the owner moves its only `BiasedArc` to another thread, the shared count
+/// becomes negative. Then the thread that makes it negative queues the object to the owner, and the
+/// owner merges it at its next `BiasedArc` operation.
+///
+/// So the data may be dropped on the owner thread some time after the last `BiasedArc` is dropped,
+/// in the worst case when the owner exits. Thus `T` should be `'static`.
+///
+/// `BiasedArc` has the same API as `Arc`, except that it supports neither `Weak` nor unsized data.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use cs431_homework::BiasedArc;
+///
+/// let five = BiasedArc::new(5);
+///
+/// // Cheap clones on the owner thread.
+/// let clones = (0..10).map(|_| BiasedArc::clone(&five)).collect::<Vec<_>>();
+///
+/// // Clones migrated to the other threads are still valid.
+/// thread::scope(|s| {
+///     for five in clones {
+///         s.spawn(move || assert_eq!(*BiasedArc::clone(&five), 5));
+///     }
+/// });
+/// ```
+pub struct BiasedArc<T> {
+    ptr: NonNull<Inner<T>>,
+    phantom: PhantomData<Inner<T>>,
+}
+
+unsafe impl<T: Sync + Send> Send for BiasedArc<T> {}
+unsafe impl<T: Sync + Send> Sync for BiasedArc<T> {}
+
+struct Inner<T> {
+    /// The queue of the owner. `None` if the object is created after the owner's registry is
+    /// destroyed, in which case the count is merged from the start.
+    owner: Option<Arc<Queue>>,
+    /// Updated only by the owner, without read-modify-write operations. Other threads may only
+    /// read it for `count`.
+    biased: AtomicUsize,
+    /// The shared count times `ONE`, plus `MERGED` and `QUEUED` flags.
+    shared: AtomicIsize,
+    data: T,
+}
+
+/// The addresses of the objects queued by the other threads to be merged by the owner.
+///
+/// An object may be merged and freed before the owner takes its address from the queue, so the
+/// address is only a hint, and the owner merges only the objects in its registry.
+#[derive(Default)]
+struct Queue {
+    head: AtomicPtr<QueueNode>,
+}
+
+struct QueueNode {
+    addr: usize,
+    next: *mut QueueNode,
+}
+
+impl Queue {
+    fn push(&self, addr: usize) {
+        let node = Box::into_raw(Box::new(QueueNode {
+            addr,
+            next: ptr::null_mut(),
+        }));
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            // SAFETY: `node` is not published yet.
+            unsafe { (*node).next = head };
+            match self
+                .head
+                .compare_exchange(head, node, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Takes all the addresses in the queue.
+    fn take_all(&self) -> Vec<usize> {
+        let mut addrs = Vec::new();
+        if self.head.load(Ordering::Relaxed).is_null() {
+            return addrs;
+        }
+        // Acquire synchronizes with the pushes, so that the nodes are initialized.
+        let mut node = self.head.swap(ptr::null_mut(), Ordering::Acquire);
+        while !node.is_null() {
+            // SAFETY: The taken nodes are owned by this thread.
+            let taken = unsafe { Box::from_raw(node) };
+            addrs.push(taken.addr);
+            node = taken.next;
+        }
+        addrs
+    }
+}
+
+impl Drop for Queue {
+    fn drop(&mut self) {
+        drop(self.take_all());
+    }
+}
+
+/// The unmerged objects of a thread, to be merged when the thread exits or when they are queued.
+struct Registry {
+    queue: Arc<Queue>,
+    /// Maps the address of each object to the function that merges it.
+    owned: RefCell<HashMap<usize, unsafe fn(usize)>>,
+}
+
+thread_local! {
+    static REGISTRY: Registry = Registry {
+        queue: Arc::default(),
+        owned: RefCell::new(HashMap::new()),
+    };
+}
+
+impl Registry {
+    /// Merges the queued objects that are not merged yet.
+    fn merge_queued(&self) {
+        for addr in self.queue.take_all() {
+            // Merging may drop the data, which may access the registry. So don't hold the borrow.
+            let merge = self.owned.borrow_mut().remove(&addr);
+            if let Some(merge) = merge {
+                // SAFETY: `addr` is an unmerged object owned by this thread, so it's not freed yet.
+                unsafe { merge(addr) };
+            }
+        }
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        // Dropping the data of an object may drop other `BiasedArc`s. They can't access the
+        // registry anymore, so they use the shared count and are merged later in this loop.
+        while let Some((&addr, &merge)) = self.owned.get_mut().iter().next() {
+            let _ = self.owned.get_mut().remove(&addr);
+            // SAFETY: `addr` is an unmerged object owned by this thread, so it's not freed yet.
+            unsafe { merge(addr) };
+        }
+    }
+}
+
+impl<T> Inner<T> {
+    /// Adds `biased` to the shared count and marks it merged. Returns `true` if the sum is zero, in
+    /// which case the caller should free the object.
+    ///
+    /// Must be called by the owner only once.
+    fn merge(&self, biased: usize) -> bool {
+        // Release the owner's accesses to the data to the thread that frees the object, and
+        // acquire the other threads' accesses in case this thread frees it.
+        let prev = self
+            .shared
+            .fetch_add(biased as isize * ONE + MERGED, Ordering::AcqRel);
+        (prev >> 2) + biased as isize == 0
+    }
+}
+
+/// Merges the object at `addr` with its biased count, and frees it if there are no references left.
+///
+/// # Safety
+///
+/// `addr` should be an unmerged object owned by the current thread, removed from its registry.
+unsafe fn merge_owned<T>(addr: usize) {
+    let inner = addr as *mut Inner<T>;
+    // SAFETY: An unmerged object is never freed.
+    let last = unsafe {
+        let biased = (*inner).biased.load(Ordering::Relaxed);
+        (*inner).merge(biased)
+    };
+    if last {
+        // SAFETY: There are no references left.
+        drop(unsafe { Box::from_raw(inner) });
+    }
+}
+
+impl<T: 'static> BiasedArc<T> {
+    /// Constructs a new `BiasedArc<T>` owned by the current thread.
+    pub fn new(data: T) -> BiasedArc<T> {
+        let owner = REGISTRY
+            .try_with(|registry| {
+                registry.merge_queued();
+                registry.queue.clone()
+            })
+            .ok();
+        let (biased, shared) = match owner {
+            Some(_) => (1, 0),
+            None => (0, ONE + MERGED),
+        };
+        let registered = owner.is_some();
+        let inner = Box::new(Inner {
+            owner,
+            biased: AtomicUsize::new(biased),
+            shared: AtomicIsize::new(shared),
+            data,
+        });
+        let ptr = NonNull::from(Box::leak(inner));
+        if registered {
+            REGISTRY.with(|registry| {
+                let _ = registry
+                    .owned
+                    .borrow_mut()
+                    .insert(ptr.as_ptr() as usize, merge_owned::<T>);
+            });
+        }
+        Self {
+            ptr,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> BiasedArc<T> {
+    #[inline]
+    fn inner(&self) -> &Inner<T> {
+        // SAFETY: The object is alive while `self` is alive.
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Returns `true` if the current thread is the owner and the biased count is not merged yet.
+    ///
+    /// On the owner, this first merges the queued objects, possibly including this one.
+    #[inline]
+    fn is_biased(&self) -> bool {
+        let inner = self.inner();
+        let Some(owner) = &inner.owner else {
+            return false;
+        };
+        REGISTRY
+            .try_with(|registry| {
+                if !Arc::ptr_eq(&registry.queue, owner) {
+                    return false;
+                }
+                registry.merge_queued();
+                // The `MERGED` bit is set by the owner, so the owner always reads the latest one.
+                inner.shared.load(Ordering::Relaxed) & MERGED == 0
+            })
+            .unwrap_or(false)
+    }
+
+    /// Frees the object.
+    ///
+    /// # Safety
+    ///
+    /// There should be no other references, and their accesses should happen before this.
+    unsafe fn drop_slow(&mut self) {
+        // SAFETY: Guaranteed by the caller.
+        drop(unsafe { Box::from_raw(self.ptr.as_ptr()) });
+    }
+
+    /// Adds a reference to the shared count.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the shared count is larger than `MAX_REFCOUNT`.
+    fn increment_shared(&self) {
+        // A new reference is made from an existing one, so there is nothing to synchronize.
+        let prev = self.inner().shared.fetch_add(ONE, Ordering::Relaxed);
+        assert!(prev >> 2 < MAX_REFCOUNT, "too many `BiasedArc`s");
+    }
+
+    /// Removes a reference from the shared count, and frees the object if it was the last one.
+    ///
+    /// The last reference is known only after the biased count is merged, i.e. when the shared
+    /// count drops to zero with the `MERGED` bit set. If the count of an unmerged object becomes
+    /// negative, the object is queued to the owner instead.
+    fn decrement_shared(&mut self) {
+        let addr = self.ptr.as_ptr() as usize;
+        let inner = self.inner();
+        let mut queue = None;
+        let mut shared = inner.shared.load(Ordering::Relaxed);
+        let new = loop {
+            let mut new = shared - ONE;
+            if shared & (MERGED | QUEUED) == 0 && new < 0 {
+                new |= QUEUED;
+                // The object may be freed by the owner right after the update, so clone the queue
+                // beforehand.
+                if queue.is_none() {
+                    queue = inner.owner.clone();
+                }
+            }
+            // Release the accesses to the data to the thread that frees the object.
+            match inner.shared.compare_exchange_weak(
+                shared,
+                new,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break new,
+                Err(current) => shared = current,
+            }
+        };
+
+        if new & !QUEUED == MERGED {
+            // Acquire the other threads' accesses to the data.
+            fence(Ordering::Acquire);
+            // SAFETY: There are no references left, and their accesses happen before this.
+            unsafe { self.drop_slow() };
+        } else if new & QUEUED != 0 && shared & QUEUED == 0 {
+            // An unmerged object always has the owner.
+            queue.expect("unmerged object without owner").push(addr);
+        }
+    }
+
+    /// Removes the object from the owner's registry. Called by the owner when merging.
+    fn unregister(&self) {
+        let _ = REGISTRY.try_with(|registry| {
+            registry
+                .owned
+                .borrow_mut()
+                .remove(&(self.ptr.as_ptr() as usize))
+        });
+    }
+
+    /// Returns a mutable reference into the given `BiasedArc` if there are no other `BiasedArc` to
+    /// the same allocation. Otherwise, return `None`.
+    ///
+    /// On the threads other than the owner, this fails until the owner merges the biased count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::BiasedArc;
+    ///
+    /// let mut x = BiasedArc::new(3);
+    /// *BiasedArc::get_mut(&mut x).unwrap() = 4;
+    /// assert_eq!(*x, 4);
+    ///
+    /// let _y = BiasedArc::clone(&x);
+    /// assert!(BiasedArc::get_mut(&mut x).is_none());
+    /// ```
+    #[inline]
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if this.is_unique() {
+            // SAFETY: There are no other references, and their accesses happen before this.
+            Some(unsafe { Self::get_mut_unchecked(this) })
+        } else {
+            None
+        }
+    }
+
+    fn is_unique(&mut self) -> bool {
+        // Acquire synchronizes with the decrements by the other threads.
+        let shared = self.inner().shared.load(Ordering::Acquire);
+        if shared & MERGED != 0 {
+            shared >> 2 == 1
+        } else if self.is_biased() {
+            // Only the owner updates the biased count, so the sum is exact.
+            self.inner().biased.load(Ordering::Relaxed) as isize + (shared >> 2) == 1
+        } else {
+            // The biased count may be changed by the owner in the meantime, so we can't tell.
+            false
+        }
+    }
+
+    /// Returns a mutable reference into the given `BiasedArc` without any check.
+    ///
+    /// # Safety
+    ///
+    /// Any other `BiasedArc` to the same allocation must not be dereferenced for the duration of
+    /// the returned borrow.
+    pub unsafe fn get_mut_unchecked(this: &mut Self) -> &mut T {
+        unsafe { &mut (*this.ptr.as_ptr()).data }
+    }
+
+    /// Gets the number of `BiasedArc`s to this allocation.
+    ///
+    /// On the threads other than the owner, this is only an estimate until the owner merges the
+    /// biased count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::BiasedArc;
+    ///
+    /// let five = BiasedArc::new(5);
+    /// let _also_five = BiasedArc::clone(&five);
+    /// assert_eq!(2, BiasedArc::count(&five));
+    /// ```
+    #[inline]
+    pub fn count(this: &Self) -> usize {
+        let shared = this.inner().shared.load(Ordering::Acquire);
+        let biased = if shared & MERGED == 0 {
+            this.inner().biased.load(Ordering::Relaxed) as isize
+        } else {
+            0
+        };
+        // There is at least `this`.
+        ((shared >> 2) + biased).max(1) as usize
+    }
+
+    /// Returns `true` if the two `BiasedArc`s point to the same allocation.
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        ptr::eq(this.ptr.as_ptr(), other.ptr.as_ptr())
+    }
+
+    /// Returns the inner value, if the given `BiasedArc` is unique.
+    ///
+    /// Otherwise, an `Err` is returned with the same `BiasedArc` that was passed in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::BiasedArc;
+    ///
+    /// let x = BiasedArc::new(3);
+    /// assert_eq!(BiasedArc::try_unwrap(x).unwrap(), 3);
+    ///
+    /// let x = BiasedArc::new(4);
+    /// let _y = BiasedArc::clone(&x);
+    /// assert_eq!(*BiasedArc::try_unwrap(x).unwrap_err(), 4);
+    /// ```
+    pub fn try_unwrap(mut this: Self) -> Result<T, Self> {
+        if !this.is_unique() {
+            return Err(this);
+        }
+        if this.is_biased() {
+            this.unregister();
+        }
+        let this = ManuallyDrop::new(this);
+        // SAFETY: There are no other references, and their accesses happen before this.
+        let inner = unsafe { Box::from_raw(this.ptr.as_ptr()) };
+        Ok(inner.data)
+    }
+}
+
+impl<T: Clone + 'static> BiasedArc<T> {
+    /// Makes a mutable reference into the given `BiasedArc`, cloning the inner value if there are
+    /// other `BiasedArc` to the same allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::BiasedArc;
+    ///
+    /// let mut data = BiasedArc::new(5);
+    /// let other_data = BiasedArc::clone(&data);
+    /// *BiasedArc::make_mut(&mut data) += 1;
+    /// assert_eq!(*data, 6);
+    /// assert_eq!(*other_data, 5);
+    /// ```
+    #[inline]
+    pub fn make_mut(this: &mut Self) -> &mut T {
+        if !this.is_unique() {
+            *this = Self::new((**this).clone());
+        }
+        // SAFETY: `this` is unique now.
+        unsafe { Self::get_mut_unchecked(this) }
+    }
+}
+
+impl<T> Clone for BiasedArc<T> {
+    /// Makes a clone of the `BiasedArc` pointer.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the number of `BiasedArc`s is larger than `isize::MAX / 16`.
+    #[inline]
+    fn clone(&self) -> BiasedArc<T> {
+        let inner = self.inner();
+        if self.is_biased() {
+            let biased = inner.biased.load(Ordering::Relaxed);
+            assert!(biased < MAX_REFCOUNT as usize, "too many `BiasedArc`s");
+            inner.biased.store(biased + 1, Ordering::Relaxed);
+        } else {
+            self.increment_shared();
+        }
+        Self {
+            ptr: self.ptr,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for BiasedArc<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner().data
+    }
+}
+
+impl<T> Drop for BiasedArc<T> {
+    /// Drops the `BiasedArc`, and frees the object if it's the last reference.
+    fn drop(&mut self) {
+        let inner = self.inner();
+        if self.is_biased() {
+            let biased = inner.biased.load(Ordering::Relaxed) - 1;
+            inner.biased.store(biased, Ordering::Relaxed);
+            if biased == 0 {
+                self.unregister();
+                if inner.merge(0) {
+                    // SAFETY: There are no references left, and `merge` acquired their accesses.
+                    unsafe { self.drop_slow() };
+                }
+            }
+        } else {
+            self.decrement_shared();
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for BiasedArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BiasedArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> fmt::Pointer for BiasedArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&(&**self), f)
+    }
+}
@@ -1,10 +1,14 @@
 //! Concurrent Owner (Cown) type.
 
-use core::cell::UnsafeCell;
+use core::cell::{Cell, RefCell, UnsafeCell};
 use core::sync::atomic::Ordering::SeqCst;
 use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize};
 use core::{fmt, hint, ptr};
-use std::sync::Arc;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, LazyLock, Mutex};
+
+use crate::hello_server::ThreadPool;
 
 /// A trait representing a `Cown`.
 ///
@@ -13,18 +17,37 @@ use std::sync::Arc;
 ///
 /// # Safety
 ///
-/// `last` should actually return the last request for the corresponding cown.
+/// `last` should actually return the last request for the corresponding cown, and `readers` and
+/// `next_writer` should be used only for the same cown.
 unsafe trait CownBase: Send {
     /// Return a pointer to the tail of this cown's request queue.
     fn last(&self) -> &AtomicPtr<Request>;
+
+    /// Return the number of read requests that currently have this cown.
+    fn readers(&self) -> &AtomicUsize;
+
+    /// Return the write request waiting for the current readers to finish.
+    fn next_writer(&self) -> &AtomicPtr<Request>;
 }
 
+/// `Request::next` of a read request that has got the cown but has no next request yet.
+///
+/// The next request gets the cown right away if it sees this in `start_enqueue`.
+const READY: *mut Request = ptr::dangling_mut();
+
 /// A request for a cown.
 pub struct Request {
-    /// Pointer to the next scheduled behavior.
-    next: AtomicPtr<Behavior>,
+    /// Pointer to the next request on the cown, or `READY`.
+    next: AtomicPtr<Request>,
+    /// The behavior of this request. Set at the start of `start_enqueue`.
+    behavior: AtomicPtr<Behavior>,
     /// Is this request scheduled?
     scheduled: AtomicBool,
+    /// Is this a read-only request?
+    ///
+    /// The read requests that are adjacent in a cown's queue get the cown together, and the
+    /// following write request gets it after all of them are released.
+    read: bool,
     /// The cown that this request wants to access.
     ///
     /// This is an `Arc` as the all exposed `CownPtr`s may have been dropped while the behavior is
@@ -32,16 +55,17 @@ pub struct Request {
     target: Arc<dyn CownBase>,
 }
 
-// SAFETY: In the basic version of BoC, user cannot get shared reference through the [`CownBase`],
-// so `Sync` bound on it is not necessary.
+// SAFETY: Shared references are given only through `CownReadPtr`, which requires `Sync`.
 unsafe impl Send for Request {}
 
 impl Request {
     /// Creates a new Request.
-    fn new(target: Arc<dyn CownBase>) -> Request {
+    fn new(target: Arc<dyn CownBase>, read: bool) -> Request {
         Request {
             next: AtomicPtr::new(ptr::null_mut()),
+            behavior: AtomicPtr::new(ptr::null_mut()),
             scheduled: AtomicBool::new(false),
+            read,
             target,
         }
     }
@@ -51,6 +75,9 @@ impl Request {
     /// Enqueues `self` onto the `target` cown. Returns once all previous behaviors on this cown has
     /// finished enqueueing on all of its required cowns. This ensures the 2PL protocol.
     ///
+    /// Store `behavior` to `self.behavior` first. When `self` gets the cown, i.e. when there's no
+    /// previous request or the previous request's `next` was `READY`, call `resolve`.
+    ///
     /// # SAFETY
     ///
     /// `behavior` must be a valid raw pointer to the behavior for `self`, and this should be the
@@ -75,12 +102,95 @@ impl Request {
     /// Called when `self` has been completed, and thus can allow the next waiting behavior to run.
     /// If there is no next behavior, then the cown's tail pointer is set to null.
     ///
+    /// The next request gets the cown with `resolve`. Read requests are released by `release_read`
+    /// instead.
+    ///
     /// # Safety
     ///
     /// `self` must have been actually completed.
     unsafe fn release(&self) {
         todo!()
     }
+
+    /// Lets `self` get the cown, and resolves it for its behavior.
+    ///
+    /// A read request also lets the next read requests get the cown, and a write request waits for
+    /// the current readers to finish.
+    ///
+    /// # Safety
+    ///
+    /// `self.behavior` must be set, and all previous requests on this cown must have been released
+    /// or be read requests that have got the cown.
+    unsafe fn resolve(&self) {
+        let mut req = self as *const Request as *mut Request;
+        loop {
+            // SAFETY: `req` has not got the cown yet, so its behavior hasn't run and it's valid.
+            let r = unsafe { &*req };
+            if !r.read {
+                // Wait for the readers. `r` counts as a reader while registering itself, so that
+                // whoever drops the count to zero resolves it. `r` may be freed after that.
+                let _ = r.target.readers().fetch_add(1, SeqCst);
+                r.target.next_writer().store(req, SeqCst);
+                r.leave_readers();
+                return;
+            }
+
+            let _ = r.target.readers().fetch_add(1, SeqCst);
+            // This should be done before `resolve_one`, after which `r` may be released and freed.
+            let next = r
+                .next
+                .compare_exchange(ptr::null_mut(), READY, SeqCst, SeqCst);
+            // SAFETY: `r.behavior` is valid as `r` is not released yet.
+            unsafe { Behavior::resolve_one(r.behavior.load(SeqCst)) };
+            match next {
+                // The next request will get the cown in `start_enqueue`.
+                Ok(_) => return,
+                // The next request gets the cown now.
+                Err(next) => req = next,
+            }
+        }
+    }
+
+    /// Decrements the number of readers, and resolves the write request waiting for the readers if
+    /// it was the last one.
+    fn leave_readers(&self) {
+        if self.target.readers().fetch_sub(1, SeqCst) != 1 {
+            return;
+        }
+        let writer = self.target.next_writer().swap(ptr::null_mut(), SeqCst);
+        if !writer.is_null() {
+            // SAFETY: `writer` has not got the cown yet, so it's valid.
+            unsafe { Behavior::resolve_one((*writer).behavior.load(SeqCst)) };
+        }
+    }
+
+    /// Release the cown of a read request.
+    ///
+    /// # Safety
+    ///
+    /// `self` must be a read request that has been actually completed.
+    unsafe fn release_read(&self) {
+        // The next request may still access `self` after it sees `READY`. Wait for it, unless
+        // there's no next request.
+        if self.next.load(SeqCst) == READY
+            && self
+                .target
+                .last()
+                .compare_exchange(
+                    self as *const Request as *mut Request,
+                    ptr::null_mut(),
+                    SeqCst,
+                    SeqCst,
+                )
+                .is_err()
+        {
+            while self.next.load(SeqCst) == READY {
+                hint::spin_loop();
+            }
+        }
+
+        self.leave_readers();
+    }
 }
 
 impl Ord for Request {
@@ -118,6 +228,10 @@ struct Cown<T: Send> {
     /// When a new node is enqueued, the enqueuer of the previous tail node will wait until the
     /// current enqueuer sets that node's `.next`.
     last: AtomicPtr<Request>,
+    /// The number of read requests that currently have this cown.
+    readers: AtomicUsize,
+    /// The write request waiting for the current readers to finish.
+    next_writer: AtomicPtr<Request>,
     /// The value of this cown.
     value: UnsafeCell<T>,
 }
@@ -127,6 +241,14 @@ unsafe impl<T: Send> CownBase for Cown<T> {
     fn last(&self) -> &AtomicPtr<Request> {
         &self.last
     }
+
+    fn readers(&self) -> &AtomicUsize {
+        &self.readers
+    }
+
+    fn next_writer(&self) -> &AtomicPtr<Request> {
+        &self.next_writer
+    }
 }
 
 /// Public interface to Cown.
@@ -152,12 +274,43 @@ impl<T: Send> CownPtr<T> {
         CownPtr {
             inner: Arc::new(Cown {
                 last: AtomicPtr::new(ptr::null_mut()),
+                readers: AtomicUsize::new(0),
+                next_writer: AtomicPtr::new(ptr::null_mut()),
                 value: UnsafeCell::new(value),
             }),
         }
     }
 }
 
+impl<T: Send + Sync> CownPtr<T> {
+    /// Requests the cown only for reading.
+    ///
+    /// In the cown list of a `when!`, this gives `&T` instead of `&mut T`. The behaviors that only
+    /// read a cown may run in parallel, while the behaviors that write to it still wait for them.
+    pub fn read(&self) -> CownReadPtr<T> {
+        CownReadPtr {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Read-only request for a cown, created by [`CownPtr::read`].
+#[derive(Debug)]
+pub struct CownReadPtr<T: Send + Sync> {
+    inner: Arc<Cown<T>>,
+}
+
+// SAFETY: `T: Sync` as the readers may get `&T` concurrently.
+unsafe impl<T: Send + Sync> Send for CownReadPtr<T> {}
+
+impl<T: Send + Sync> Clone for CownReadPtr<T> {
+    fn clone(&self) -> Self {
+        CownReadPtr {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 type BehaviorThunk = Box<dyn FnOnce() + Send>;
 
 /// Behavior that captures the content of a when body.
@@ -168,6 +321,8 @@ struct Behavior {
     count: AtomicUsize,
     /// The requests for this behavior.
     requests: Vec<Request>,
+    /// The runtime that runs this behavior.
+    runtime: BocRuntime,
 }
 
 impl Behavior {
@@ -182,7 +337,7 @@ impl Behavior {
     /// Resolves a single outstanding request for `this`.
     ///
     /// Called when a request for `this` is at the head of the queue for a particular cown. If it is
-    /// the last request, then the thunk is scheduled.
+    /// the last request, then the thunk is scheduled with `BocRuntime::execute` of its runtime.
     ///
     /// # Safety
     ///
@@ -198,16 +353,18 @@ impl fmt::Debug for Behavior {
             .field("thunk", &"BehaviorThunk")
             .field("count", &self.count)
             .field("requests", &self.requests)
+            .field("runtime", &self.runtime)
             .finish()
     }
 }
 
 // TODO: terminator?
 impl Behavior {
-    fn new<C, F>(cowns: C, f: F) -> Behavior
+    /// Creates a behavior that runs on `runtime`.
+    fn new<C, F>(cowns: C, f: F, runtime: BocRuntime) -> Behavior
     where
         C: CownPtrs + Send + 'static,
-        F: for<'l> Fn(C::CownRefs<'l>) + Send + 'static,
+        F: for<'l> FnOnce(C::CownRefs<'l>) + Send + 'static,
     {
         todo!()
     }
@@ -262,7 +419,7 @@ unsafe impl<T: Send + 'static, Ts: CownPtrs> CownPtrs for (CownPtr<T>, Ts) {
     fn requests(&self) -> Vec<Request> {
         let mut rs = self.1.requests();
         let cown_base: Arc<dyn CownBase> = self.0.inner.clone();
-        rs.push(Request::new(cown_base));
+        rs.push(Request::new(cown_base, false));
         rs
     }
 
@@ -271,6 +428,24 @@ unsafe impl<T: Send + 'static, Ts: CownPtrs> CownPtrs for (CownPtr<T>, Ts) {
     }
 }
 
+unsafe impl<T: Send + Sync + 'static, Ts: CownPtrs> CownPtrs for (CownReadPtr<T>, Ts) {
+    type CownRefs<'l>
+        = (&'l T, Ts::CownRefs<'l>)
+    where
+        Self: 'l;
+
+    fn requests(&self) -> Vec<Request> {
+        let mut rs = self.1.requests();
+        let cown_base: Arc<dyn CownBase> = self.0.inner.clone();
+        rs.push(Request::new(cown_base, true));
+        rs
+    }
+
+    unsafe fn get_mut<'l>(self) -> Self::CownRefs<'l> {
+        unsafe { (&*self.0.inner.value.get(), self.1.get_mut()) }
+    }
+}
+
 unsafe impl<T: Send + 'static> CownPtrs for Vec<CownPtr<T>> {
     type CownRefs<'l>
         = Vec<&'l mut T>
@@ -278,7 +453,9 @@ unsafe impl<T: Send + 'static> CownPtrs for Vec<CownPtr<T>> {
         Self: 'l;
 
     fn requests(&self) -> Vec<Request> {
-        self.iter().map(|x| Request::new(x.inner.clone())).collect()
+        self.iter()
+            .map(|x| Request::new(x.inner.clone(), false))
+            .collect()
     }
 
     unsafe fn get_mut<'l>(self) -> Self::CownRefs<'l> {
@@ -288,13 +465,377 @@ unsafe impl<T: Send + 'static> CownPtrs for Vec<CownPtr<T>> {
     }
 }
 
-/// Creates a `Behavior` and schedules it. Used by "When" block.
+unsafe impl<T: Send + Sync + 'static> CownPtrs for Vec<CownReadPtr<T>> {
+    type CownRefs<'l>
+        = Vec<&'l T>
+    where
+        Self: 'l;
+
+    fn requests(&self) -> Vec<Request> {
+        self.iter()
+            .map(|x| Request::new(x.inner.clone(), true))
+            .collect()
+    }
+
+    unsafe fn get_mut<'l>(self) -> Self::CownRefs<'l> {
+        self.iter()
+            .map(|x| unsafe { &*x.inner.value.get() })
+            .collect()
+    }
+}
+
+/// Executes the jobs of the behaviors that are ready to run.
+pub trait Executor: Send + Sync + 'static {
+    /// Runs `job` eventually.
+    fn execute(&self, job: Box<dyn FnOnce() + Send>);
+}
+
+/// The global thread pool of `rayon`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlobalRayon;
+
+impl Executor for GlobalRayon {
+    fn execute(&self, job: Box<dyn FnOnce() + Send>) {
+        rayon::spawn(job);
+    }
+}
+
+/// A work-stealing thread pool.
+impl Executor for rayon::ThreadPool {
+    fn execute(&self, job: Box<dyn FnOnce() + Send>) {
+        self.spawn(job);
+    }
+}
+
+/// A fixed-size thread pool.
+impl Executor for ThreadPool {
+    fn execute(&self, job: Box<dyn FnOnce() + Send>) {
+        ThreadPool::execute(self, job);
+    }
+}
+
+/// Number of the behaviors that are scheduled but not finished.
+#[derive(Debug, Default)]
+struct Pending {
+    count: Mutex<usize>,
+    idle: Condvar,
+}
+
+impl Pending {
+    fn start(&self) {
+        *self.count.lock().unwrap() += 1;
+    }
+
+    fn finish(&self) {
+        let mut count = self.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.idle.notify_all();
+        }
+    }
+
+    fn wait_empty(&self) {
+        let mut count = self.count.lock().unwrap();
+        while *count != 0 {
+            count = self.idle.wait(count).unwrap();
+        }
+    }
+}
+
+/// Finishes a behavior when dropped, even if its thunk panics, so that `wait_idle` doesn't hang.
+struct Finish {
+    /// The runtime of the thread before running the behavior.
+    prev: Option<BocRuntime>,
+    pending: Arc<Pending>,
+}
+
+impl Drop for Finish {
+    fn drop(&mut self) {
+        // Drop the handle before finishing so that `wait_idle` is not followed by dropping the
+        // runtime here.
+        drop(CURRENT.replace(self.prev.take()));
+        self.pending.finish();
+    }
+}
+
+/// Runtime that runs the behaviors on an [`Executor`].
+///
+/// This is a handle to the runtime, and its clones refer to the same runtime. The behaviors
+/// scheduled by `when!` or [`run_when`] run on the runtime of the behavior that schedules them, or
+/// on the global runtime with [`GlobalRayon`] if not scheduled by a behavior.
+///
+/// If the executor joins its threads when dropped, call [`BocRuntime::wait_idle`] before dropping
+/// the runtime so that the executor is not dropped in its own thread.
+#[derive(Clone)]
+pub struct BocRuntime {
+    executor: Arc<dyn Executor>,
+    pending: Arc<Pending>,
+}
+
+impl fmt::Debug for BocRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BocRuntime")
+            .field("pending", &self.pending.count)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The global runtime.
+static GLOBAL: LazyLock<BocRuntime> = LazyLock::new(|| BocRuntime::new(GlobalRayon));
+
+thread_local! {
+    /// The runtime of the behavior running on this thread.
+    static CURRENT: RefCell<Option<BocRuntime>> = const { RefCell::new(None) };
+
+    /// The panic of the thunk running on this thread, resumed after its requests are released.
+    static PANIC: Cell<Option<Box<dyn Any + Send>>> = const { Cell::new(None) };
+}
+
+impl BocRuntime {
+    /// Creates a new runtime that runs the behaviors on `executor`.
+    pub fn new(executor: impl Executor) -> Self {
+        Self {
+            executor: Arc::new(executor),
+            pending: Arc::new(Pending::default()),
+        }
+    }
+
+    /// Returns the runtime of the behavior running on this thread, or the global runtime.
+    pub fn current() -> Self {
+        CURRENT
+            .with_borrow(Clone::clone)
+            .unwrap_or_else(|| GLOBAL.clone())
+    }
+
+    /// Returns `true` if the two handles refer to the same runtime.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.pending, &other.pending)
+    }
+
+    /// Creates a `Behavior` and schedules it on this runtime.
+    ///
+    /// If the thunk panics, the cowns are still released, so they may be left in an inconsistent
+    /// state. The panic is resumed on the executor's thread after that.
+    pub fn run_when<C, F>(&self, cowns: C, f: F)
+    where
+        C: CownPtrs + Send + 'static,
+        F: for<'l> FnOnce(C::CownRefs<'l>) + Send + 'static,
+    {
+        self.pending.start();
+        // Catch the panic so that the job releases the requests, and resume it in `execute`.
+        let f = move |refs: C::CownRefs<'_>| {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| f(refs))) {
+                PANIC.set(Some(payload));
+            }
+        };
+        Behavior::new(cowns, f, self.clone()).schedule();
+    }
+
+    /// Creates a `Behavior` and schedules it on this runtime. Returns the promise of its result.
+    pub fn run_when_promise<C, F, R>(&self, cowns: C, f: F) -> Promise<R>
+    where
+        C: CownPtrs + Send + 'static,
+        F: for<'l> FnOnce(C::CownRefs<'l>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let promise = Promise::new(self.clone());
+        let fulfill = Fulfill(Some(promise.inner.clone()));
+        self.run_when(cowns, move |refs| fulfill.fulfill(f(refs)));
+        promise
+    }
+
+    /// Blocks the current thread until all the behaviors scheduled on this runtime have finished,
+    /// including the ones scheduled by them.
+    ///
+    /// NOTE: This deadlocks if called by a behavior on this runtime.
+    pub fn wait_idle(&self) {
+        self.pending.wait_empty();
+    }
+
+    /// Runs `job`, which runs the thunk of a behavior on this runtime and releases its requests.
+    fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        let runtime = self.clone();
+        let pending = self.pending.clone();
+        self.executor.execute(Box::new(move || {
+            let _finish = Finish {
+                prev: CURRENT.replace(Some(runtime)),
+                pending,
+            };
+            job();
+            if let Some(payload) = PANIC.take() {
+                panic::resume_unwind(payload);
+            }
+        }));
+    }
+}
+
+/// Creates a `Behavior` and schedules it on the current runtime. Used by "When" block.
+///
+/// See [`BocRuntime::current`] for the current runtime.
 pub fn run_when<C, F>(cowns: C, f: F)
 where
     C: CownPtrs + Send + 'static,
-    F: for<'l> Fn(C::CownRefs<'l>) + Send + 'static,
+    F: for<'l> FnOnce(C::CownRefs<'l>) + Send + 'static,
+{
+    BocRuntime::current().run_when(cowns, f);
+}
+
+/// Creates a `Behavior` and schedules it on the current runtime. Returns the promise of its result.
+///
+/// See [`BocRuntime::current`] for the current runtime.
+pub fn run_when_promise<C, F, R>(cowns: C, f: F) -> Promise<R>
+where
+    C: CownPtrs + Send + 'static,
+    F: for<'l> FnOnce(C::CownRefs<'l>) -> R + Send + 'static,
+    R: Send + 'static,
 {
-    Behavior::new(cowns, f).schedule();
+    BocRuntime::current().run_when_promise(cowns, f)
+}
+
+/// The result of a behavior, or a callback waiting for it.
+struct PromiseState<R> {
+    /// `Some(None)` if the behavior panicked.
+    value: Option<Option<R>>,
+    /// Called with `None` if the behavior panicked.
+    callback: Option<Box<dyn FnOnce(Option<R>) + Send>>,
+}
+
+struct PromiseInner<R> {
+    state: Mutex<PromiseState<R>>,
+    ready: Condvar,
+}
+
+impl<R> PromiseInner<R> {
+    /// Sets the result, or gives it to the callback if any. `None` if the behavior panicked.
+    fn fulfill(&self, value: Option<R>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(callback) = state.callback.take() {
+            drop(state);
+            callback(value);
+            return;
+        }
+        state.value = Some(value);
+        self.ready.notify_all();
+    }
+}
+
+/// Fulfills a promise with the result of a behavior. If dropped before that, e.g. when the
+/// behavior panics, marks the promise as panicked.
+struct Fulfill<R>(Option<Arc<PromiseInner<R>>>);
+
+impl<R> Fulfill<R> {
+    fn fulfill(mut self, value: R) {
+        self.0.take().unwrap().fulfill(Some(value));
+    }
+}
+
+impl<R> Drop for Fulfill<R> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.0.take() {
+            inner.fulfill(None);
+        }
+    }
+}
+
+/// The result of a behavior, which is available once the behavior has finished.
+///
+/// Created by [`run_when_promise`] or `when_promise!`.
+pub struct Promise<R> {
+    inner: Arc<PromiseInner<R>>,
+    /// The runtime of the behavior.
+    runtime: BocRuntime,
+}
+
+impl<R> fmt::Debug for Promise<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Promise")
+            .field("ready", &self.inner.state.lock().unwrap().value.is_some())
+            .field("runtime", &self.runtime)
+            .finish()
+    }
+}
+
+impl<R: Send + 'static> Promise<R> {
+    fn new(runtime: BocRuntime) -> Self {
+        Self {
+            inner: Arc::new(PromiseInner {
+                state: Mutex::new(PromiseState {
+                    value: None,
+                    callback: None,
+                }),
+                ready: Condvar::new(),
+            }),
+            runtime,
+        }
+    }
+
+    /// Blocks the current thread until the result is available, and returns it.
+    ///
+    /// NOTE: This may deadlock if called by a behavior, as it blocks a thread of the runtime.
+    /// Consider `then` instead.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the behavior panicked.
+    pub fn wait(self) -> R {
+        let mut state = self.inner.state.lock().unwrap();
+        loop {
+            if let Some(value) = state.value.take() {
+                drop(state);
+                return value.unwrap_or_else(|| Self::panicked());
+            }
+            state = self.inner.ready.wait(state).unwrap();
+        }
+    }
+
+    /// Returns the result if it is available, or `self` otherwise.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the behavior panicked.
+    pub fn try_get(self) -> Result<R, Self> {
+        let value = self.inner.state.lock().unwrap().value.take();
+        match value {
+            Some(value) => Ok(value.unwrap_or_else(|| Self::panicked())),
+            None => Err(self),
+        }
+    }
+
+    fn panicked() -> ! {
+        panic!("the behavior of the promise panicked");
+    }
+
+    /// Schedules a behavior on `cowns` that runs with the result once it is available, on the
+    /// runtime of `self`. Returns the promise of its result.
+    ///
+    /// If the behavior of `self` panics, the new behavior doesn't run, and the returned promise is
+    /// marked as panicked as well.
+    pub fn then<C, F, S>(self, cowns: C, f: F) -> Promise<S>
+    where
+        C: CownPtrs + Send + 'static,
+        F: for<'l> FnOnce(R, C::CownRefs<'l>) -> S + Send + 'static,
+        S: Send + 'static,
+    {
+        let promise = Promise::new(self.runtime.clone());
+        let fulfill = Fulfill(Some(promise.inner.clone()));
+        let runtime = self.runtime;
+        let schedule = move |value: Option<R>| {
+            // If the value is `None`, dropping `fulfill` marks the new promise as panicked.
+            if let Some(value) = value {
+                runtime.run_when(cowns, move |refs| fulfill.fulfill(f(value, refs)));
+            }
+        };
+
+        let mut state = self.inner.state.lock().unwrap();
+        match state.value.take() {
+            Some(value) => {
+                drop(state);
+                schedule(value);
+            }
+            None => state.callback = Some(Box::new(schedule)),
+        }
+        promise
+    }
 }
 
 /// from <https://docs.rs/tuple_list/latest/tuple_list/>
@@ -316,13 +857,32 @@ macro_rules! tuple_list {
 }
 
 /// "When" block.
+///
+/// A cown can be given as `c.read()` to only read it. The behavior runs on the current runtime, or
+/// on the given runtime with `when!(in runtime; ...)`.
 #[macro_export]
 macro_rules! when {
-    ( $( $cs:ident ),* ; $( $gs:ident ),* ; $thunk:expr_2021 ) => {{
+    ( in $rt:expr_2021 ; $( $cs:expr_2021 ),* ; $( $gs:ident ),* ; $thunk:expr_2021 ) => {{
+        $rt.run_when(tuple_list!($($cs.clone()),*), move |tuple_list!($($gs),*)| $thunk);
+    }};
+    ( $( $cs:expr_2021 ),* ; $( $gs:ident ),* ; $thunk:expr_2021 ) => {{
         run_when(tuple_list!($($cs.clone()),*), move |tuple_list!($($gs),*)| $thunk);
     }};
 }
 
+/// "When" block that returns the [`Promise`] of the value of the thunk.
+///
+/// Takes the same arguments as `when!`.
+#[macro_export]
+macro_rules! when_promise {
+    ( in $rt:expr_2021 ; $( $cs:expr_2021 ),* ; $( $gs:ident ),* ; $thunk:expr_2021 ) => {{
+        $rt.run_when_promise(tuple_list!($($cs.clone()),*), move |tuple_list!($($gs),*)| $thunk)
+    }};
+    ( $( $cs:expr_2021 ),* ; $( $gs:ident ),* ; $thunk:expr_2021 ) => {{
+        run_when_promise(tuple_list!($($cs.clone()),*), move |tuple_list!($($gs),*)| $thunk)
+    }};
+}
+
 #[test]
 fn boc() {
     let c1 = CownPtr::new(0);
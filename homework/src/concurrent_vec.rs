@@ -0,0 +1,133 @@
+//! Lock-free vector.
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::*;
+
+use crossbeam_epoch::Owned;
+
+use crate::GrowableArray;
+
+/// Lock-free append-only vector, built on [`GrowableArray`].
+///
+/// `push` reserves an index by incrementing the length, and then stores the element to the slot
+/// at the index. Elements are never moved or removed until the vector is dropped, so the indices
+/// and the references to the elements are stable, e.g. for allocating IDs.
+///
+/// # Example
+///
+/// ```
+/// use cs431_homework::ConcurrentVec;
+///
+/// let vec = ConcurrentVec::new();
+/// assert_eq!(vec.push("a"), 0);
+/// assert_eq!(vec.push("b"), 1);
+/// assert_eq!(vec.get(1), Some(&"b"));
+/// assert_eq!(vec.len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentVec<T> {
+    /// Slots of the elements. Each slot owns its element.
+    array: GrowableArray<T>,
+    /// Number of indices reserved by `push`.
+    len: AtomicUsize,
+}
+
+impl<T> Default for ConcurrentVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ConcurrentVec<T> {
+    /// Creates a new empty vector.
+    pub fn new() -> Self {
+        Self {
+            array: GrowableArray::new(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends an element to the back of the vector, and returns its index.
+    pub fn push(&self, value: T) -> usize {
+        let index = self.len.fetch_add(1, Relaxed);
+        let guard = crossbeam_epoch::pin();
+        let slot = self.array.get(index, &guard);
+        debug_assert!(slot.load(Relaxed, &guard).is_null());
+        // Release: to publish the element to `get`.
+        slot.store(Owned::new(value), Release);
+        index
+    }
+
+    /// Returns the element at `index`, or `None` if `index` is out of bounds or the element is
+    /// still being pushed.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        let guard = crossbeam_epoch::pin();
+        let element = self.array.get(index, &guard).load(Acquire, &guard);
+        // SAFETY: Elements are freed only when the vector is dropped, so the reference is valid
+        // for the lifetime of `&self`, not only of `guard`.
+        unsafe { element.as_raw().as_ref() }
+    }
+
+    /// Returns the number of elements, including those still being pushed.
+    pub fn len(&self) -> usize {
+        self.len.load(Relaxed)
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the indices and elements of the vector.
+    ///
+    /// The iterator visits the indices below the length at its creation, skipping the elements
+    /// still being pushed.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            vec: self,
+            index: 0,
+            len: self.len(),
+        }
+    }
+}
+
+impl<T> Drop for ConcurrentVec<T> {
+    /// Drops the elements. The segments are then deallocated by the inner `GrowableArray`.
+    fn drop(&mut self) {
+        // SAFETY: We have unique ownership of the vector and its elements via `&mut self`.
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        for index in 0..*self.len.get_mut() {
+            let element = self.array.get(index, guard).load(Relaxed, guard);
+            if !element.is_null() {
+                drop(unsafe { element.into_owned() });
+            }
+        }
+    }
+}
+
+/// Iterator over the indices and elements of a [`ConcurrentVec`], created by
+/// [`ConcurrentVec::iter`].
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    vec: &'a ConcurrentVec<T>,
+    index: usize,
+    len: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.len {
+            let index = self.index;
+            self.index += 1;
+            if let Some(element) = self.vec.get(index) {
+                return Some((index, element));
+            }
+        }
+        None
+    }
+}
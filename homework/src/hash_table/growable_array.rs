@@ -167,6 +167,42 @@ impl<T> Segment<T> {
     unsafe fn deallocate(self, height: usize) {
         todo!()
     }
+
+    /// Detaches the child segments that are only for the indices `capacity..`, and retires them.
+    ///
+    /// # Safety
+    ///
+    /// - `self` must actually have height `height`.
+    /// - There should be no concurrent accesses to the indices `capacity..`.
+    unsafe fn shrink(&self, height: usize, capacity: usize, guard: &Guard) {
+        if height == 1 {
+            return;
+        }
+
+        let child_logsize = SEGMENT_LOGSIZE * (height - 1);
+        // SAFETY: `self` is an inner segment as `height > 1`.
+        for (i, child) in unsafe { self.children.iter() }.enumerate() {
+            // The children beyond the range of `usize` are never used.
+            let start = i.saturating_mul(1 << child_logsize);
+            if start >= capacity {
+                let child = child.swap(Shared::null(), AcqRel, guard);
+                if !child.is_null() {
+                    // SAFETY: `child` is detached, and the other threads may access it only until
+                    // they unpin.
+                    unsafe {
+                        guard.defer_unchecked(move || {
+                            (*child.into_owned().into_box()).deallocate(height - 1)
+                        })
+                    };
+                }
+            } else if capacity - start < 1 << child_logsize {
+                let child = child.load(Acquire, guard);
+                if let Some(child) = unsafe { child.as_ref() } {
+                    unsafe { child.shrink(height - 1, capacity - start, guard) };
+                }
+            }
+        }
+    }
 }
 
 impl<T> Debug for Segment<T> {
@@ -196,6 +232,82 @@ impl<T> GrowableArray<T> {
         }
     }
 
+    /// Frees the segments that are only for the indices `capacity..`, and lowers the height of the
+    /// array if the indices `..capacity` fit in a lower one. The segments are retired through
+    /// `guard`, so that the concurrent accesses to the indices `..capacity` are safe.
+    ///
+    /// As in `drop`, the elements at the indices `capacity..` are not dropped. They should be
+    /// removed before calling this, and there should be no concurrent accesses to them.
+    pub fn shrink_to(&self, capacity: usize, guard: &Guard) {
+        let root = self.root.load(Acquire, guard);
+        let height = root.tag();
+        let Some(segment) = (unsafe { root.as_ref() }) else {
+            return;
+        };
+
+        if capacity == 0 {
+            if self
+                .root
+                .compare_exchange(root, Shared::null(), AcqRel, Acquire, guard)
+                .is_ok()
+            {
+                // SAFETY: The whole tree is detached.
+                unsafe {
+                    guard.defer_unchecked(move || {
+                        (*root.with_tag(0).into_owned().into_box()).deallocate(height)
+                    })
+                };
+            }
+            return;
+        }
+
+        // SAFETY: The root segment has height `root.tag()`.
+        unsafe { segment.shrink(height, capacity, guard) };
+
+        // The index `capacity - 1` needs this many levels.
+        let bits = (usize::BITS - (capacity - 1).leading_zeros()) as usize;
+        let target = bits.div_ceil(SEGMENT_LOGSIZE).max(1);
+
+        // Now only the first child of the root is left. Replace the root with it.
+        let mut root = root;
+        while root.tag() > target {
+            // SAFETY: `root` is protected by `guard`, and is an inner segment as its height > 1.
+            let first = unsafe { &root.deref().children[0] };
+            let mut child = first.load(Acquire, guard);
+            if child.is_null() {
+                // A concurrent access to `..capacity` may install a child to the old root after it
+                // is detached, and that child would be lost. So install an empty child first, so
+                // that both the old and the new root lead to the same segment.
+                child = match first.compare_exchange(
+                    Shared::null(),
+                    Segment::new(),
+                    AcqRel,
+                    Acquire,
+                    guard,
+                ) {
+                    Ok(child) => child,
+                    Err(e) => e.current,
+                };
+            }
+            let child = child.with_tag(root.tag() - 1);
+            match self
+                .root
+                .compare_exchange(root, child, AcqRel, Acquire, guard)
+            {
+                Ok(_) => {
+                    // Only the root segment itself is freed, and the children are kept. Since
+                    // segments don't implement `Drop`, dropping it doesn't free the children.
+                    //
+                    // SAFETY: `root` is detached. The other threads may still access its first
+                    // child through it, but only until they unpin.
+                    unsafe { guard.defer_destroy(root.with_tag(0)) };
+                    root = child;
+                }
+                Err(e) => root = e.current,
+            }
+        }
+    }
+
     /// Returns the reference to the `Atomic` pointer at `index`. Allocates new segments if
     /// necessary.
     pub fn get<'g>(&self, index: usize, guard: &'g Guard) -> &'g Atomic<T> {
@@ -1,7 +1,9 @@
 //! Lock-free hash table based on <https://dl.acm.org/doi/abs/10.1145/1147954.1147958>
 
 mod growable_array;
+mod open_addressing;
 mod split_ordered_list;
 
 pub use growable_array::GrowableArray;
+pub use open_addressing::OpenAddressingMap;
 pub use split_ordered_list::SplitOrderedList;
@@ -0,0 +1,554 @@
+//! Lock-free hash map with open addressing.
+
+use core::hash::{BuildHasher, Hash};
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::*;
+use std::collections::HashSet;
+use std::hash::RandomState;
+
+use crossbeam_epoch::{Atomic, Guard, Owned, Shared, unprotected};
+
+use crate::ConcurrentMap;
+
+/// Tag of a deleted entry. The slot keeps the entry for its key, until the key is inserted again.
+const TOMBSTONE: usize = 1;
+/// Tag of a slot being copied to the next table. A frozen slot can no longer be updated.
+const FROZEN: usize = 2;
+/// Tag of a null slot whose entry has been copied to the next table.
+const MOVED: usize = TOMBSTONE | FROZEN;
+
+/// The number of slots of the smallest table.
+const MIN_LEN: usize = 16;
+/// The number of slots copied at once while resizing.
+const COPY_CHUNK: usize = 64;
+
+/// Lock-free map from `K` to `V` with open addressing, where the keys are hashed with `S`.
+///
+/// This is a variant of Cliff Click's [lock-free hash table][click], which stores the items in a
+/// table of slots with linear probing instead of a list of nodes.
+///
+/// - Each slot points to an entry of a key and a value. Once a slot is used for a key, it is used
+///   only for that key in that table. A deleted entry is marked as a tombstone, and is replaced
+///   when the key is inserted again.
+///
+/// - When too many slots are used, a new table is allocated, and the threads updating the map
+///   cooperatively copy the old table to the new one, a chunk of slots at a time. To copy a slot,
+///   it is first frozen so that it can no longer be updated, and then its live entry is moved to
+///   the new table. Updates to a key go to the newest table, after its slot in the old table is
+///   copied. The old table is retired when all its slots are copied.
+///
+/// The values of the deleted items are dropped when their keys are inserted again, or their tables
+/// are retired.
+///
+/// [click]: https://web.stanford.edu/class/ee380/Abstracts/070221_LockFreeHash.pdf
+#[derive(Debug)]
+pub struct OpenAddressingMap<K, V, S = RandomState> {
+    /// The oldest table that is not retired yet.
+    table: Atomic<Table<K, V>>,
+    /// Number of items.
+    count: AtomicUsize,
+    /// Builds the hashers of the keys.
+    hash_builder: S,
+}
+
+/// Entry of an item.
+///
+/// `hash` also makes the entries aligned to at least 4 bytes, so that the pointers to them have 2
+/// bits for the tags.
+#[derive(Debug)]
+struct Entry<K, V> {
+    hash: usize,
+    key: K,
+    value: V,
+}
+
+/// Table of slots.
+#[derive(Debug)]
+struct Table<K, V> {
+    /// Pointers to the entries. The number of slots is a power of two.
+    slots: Box<[Atomic<Entry<K, V>>]>,
+    /// Number of slots used for keys.
+    used: AtomicUsize,
+    /// The table to which this one is being copied.
+    next: Atomic<Table<K, V>>,
+    /// Number of slots claimed to be copied.
+    claimed: AtomicUsize,
+    /// Number of slots copied.
+    copied: AtomicUsize,
+}
+
+/// Result of searching a table for a key.
+enum Search<'g, K, V> {
+    /// The slot for the key, with its entry.
+    Found(&'g Atomic<Entry<K, V>>, Shared<'g, Entry<K, V>>),
+    /// The first empty slot in the probe sequence, with its null pointer, which may be frozen.
+    Vacant(&'g Atomic<Entry<K, V>>, Shared<'g, Entry<K, V>>),
+    /// There are no empty slots.
+    Full,
+}
+
+impl<K, V> Table<K, V> {
+    /// Creates a new table with `len` empty slots.
+    fn new(len: usize) -> Self {
+        debug_assert!(len.is_power_of_two());
+        Self {
+            slots: (0..len).map(|_| Atomic::null()).collect(),
+            used: AtomicUsize::new(0),
+            next: Atomic::null(),
+            claimed: AtomicUsize::new(0),
+            copied: AtomicUsize::new(0),
+        }
+    }
+
+    /// Searches the slots from `hash` for `key`.
+    ///
+    /// Also returns whether it has seen moved slots, in which case the key may have been moved to
+    /// the next table.
+    fn search<'g>(&'g self, hash: usize, key: &K, guard: &'g Guard) -> (Search<'g, K, V>, bool)
+    where
+        K: Eq,
+    {
+        let mask = self.slots.len() - 1;
+        let mut moved = false;
+        for i in 0..self.slots.len() {
+            let slot = &self.slots[hash.wrapping_add(i) & mask];
+            let entry = slot.load(Acquire, guard);
+            // SAFETY: An entry is retired only after it is removed from the slots of the tables
+            // that are not retired.
+            match unsafe { entry.as_ref() } {
+                Some(e) if e.hash == hash && e.key == *key => {
+                    return (Search::Found(slot, entry), moved);
+                }
+                Some(_) => {}
+                None if entry.tag() == MOVED => moved = true,
+                None => return (Search::Vacant(slot, entry), moved),
+            }
+        }
+        (Search::Full, moved)
+    }
+}
+
+impl<K, V> Drop for Table<K, V> {
+    /// Drops the entries, except for the frozen live entries, which are owned by the next table.
+    fn drop(&mut self) {
+        // SAFETY: We have unique ownership of the table and its entries via `&mut self`.
+        let guard = unsafe { unprotected() };
+        for slot in &self.slots {
+            let entry = slot.load(Relaxed, guard);
+            if !entry.is_null() && entry.tag() != FROZEN {
+                drop(unsafe { entry.with_tag(0).into_owned() });
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Default for OpenAddressingMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: Hash + Eq, V> OpenAddressingMap<K, V> {
+    /// Creates a new map.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> OpenAddressingMap<K, V, S> {
+    /// Creates a new map which uses the given hash builder to hash keys.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            table: Atomic::new(Table::new(MIN_LEN)),
+            count: AtomicUsize::new(0),
+            hash_builder,
+        }
+    }
+
+    /// Returns the number of items in the map.
+    ///
+    /// The concurrent insertions and deletions may or may not be counted.
+    pub fn len(&self) -> usize {
+        self.count.load(Relaxed)
+    }
+
+    /// Returns `true` if the map contains no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn hash(&self, key: &K) -> usize {
+        self.hash_builder.hash_one(key) as usize
+    }
+
+    /// Counts a slot of `table` newly used for a key, and starts resizing it if it's too full.
+    fn claim(&self, table: &Table<K, V>, guard: &Guard) {
+        let len = table.slots.len();
+        if table.used.fetch_add(1, Relaxed) + 1 > len / 4 * 3 {
+            self.start_resize(table, guard);
+        }
+    }
+
+    /// Allocates the next table of `table`, if not allocated yet.
+    fn start_resize(&self, table: &Table<K, V>, guard: &Guard) {
+        if !table.next.load(Acquire, guard).is_null() {
+            return;
+        }
+        // The count may be off during concurrent updates, so keep it in a sane range.
+        let count = self.count.load(Relaxed).min(table.slots.len());
+        let len = (count * 2).next_power_of_two().max(MIN_LEN);
+        let _ = table.next.compare_exchange(
+            Shared::null(),
+            Owned::new(Table::new(len)),
+            AcqRel,
+            Acquire,
+            guard,
+        );
+    }
+
+    /// Freezes `slot` of `table` so that it can no longer be updated, and copies its live entry to
+    /// the next table.
+    fn copy_slot(&self, table: &Table<K, V>, slot: &Atomic<Entry<K, V>>, guard: &Guard) {
+        let mut entry = slot.load(Acquire, guard);
+        while entry.tag() & FROZEN == 0 {
+            let frozen = entry.with_tag(entry.tag() | FROZEN);
+            entry = match slot.compare_exchange(entry, frozen, AcqRel, Acquire, guard) {
+                Ok(_) => frozen,
+                Err(e) => e.current,
+            };
+        }
+        if entry.is_null() || entry.tag() & TOMBSTONE != 0 {
+            return;
+        }
+
+        // SAFETY: A slot is frozen only after the next table is allocated.
+        let next = unsafe { table.next.load(Acquire, guard).deref() };
+        self.copy_entry(next, entry.with_tag(0), guard);
+        // Now the next table owns the entry. Remove it from the slot, so that it is no longer
+        // reachable once the next table retires it.
+        let _ = slot.compare_exchange(
+            entry,
+            Shared::null().with_tag(MOVED),
+            Release,
+            Relaxed,
+            guard,
+        );
+    }
+
+    /// Inserts `entry` copied from the previous table into `table`, unless its key has already been
+    /// written to `table`, or copied further to the next tables.
+    fn copy_entry<'g>(
+        &self,
+        mut table: &'g Table<K, V>,
+        entry: Shared<'g, Entry<K, V>>,
+        guard: &'g Guard,
+    ) {
+        // SAFETY: `entry` is not retired before the slot it's copied from is marked as moved.
+        let e = unsafe { entry.deref() };
+        loop {
+            let (search, _) = table.search(e.hash, &e.key, guard);
+            let next = table.next.load(Acquire, guard);
+            match search {
+                // Copied by another thread, and maybe updated after that.
+                Search::Found(..) => return,
+                Search::Vacant(slot, current) if next.is_null() => {
+                    if slot
+                        .compare_exchange(current, entry, Release, Relaxed, guard)
+                        .is_ok()
+                    {
+                        self.claim(table, guard);
+                        return;
+                    }
+                    continue;
+                }
+                Search::Vacant(slot, _) => self.copy_slot(table, slot, guard),
+                Search::Full => self.start_resize(table, guard),
+            }
+            // SAFETY: The next table is allocated by now.
+            table = unsafe { table.next.load(Acquire, guard).deref() };
+        }
+    }
+
+    /// Copies a chunk of the slots of `table` to the next table, and promotes the next table if
+    /// all slots are copied.
+    fn help_copy<'g>(&self, table: Shared<'g, Table<K, V>>, guard: &'g Guard) {
+        // SAFETY: `table` is protected by `guard`.
+        let t = unsafe { table.deref() };
+        let len = t.slots.len();
+        let start = t.claimed.fetch_add(COPY_CHUNK, Relaxed);
+        if start >= len {
+            return;
+        }
+        let end = (start + COPY_CHUNK).min(len);
+        for slot in &t.slots[start..end] {
+            self.copy_slot(t, slot, guard);
+        }
+        if t.copied.fetch_add(end - start, AcqRel) + (end - start) == len {
+            self.promote(table, guard);
+        }
+    }
+
+    /// Replaces `table` with its next table, to which all its slots are copied. Continues to the
+    /// next tables that are also copied.
+    fn promote<'g>(&self, mut table: Shared<'g, Table<K, V>>, guard: &'g Guard) {
+        loop {
+            // SAFETY: `table` is protected by `guard`, and all its slots are copied to the next
+            // table.
+            let next = unsafe { table.deref() }.next.load(Acquire, guard);
+            if self
+                .table
+                .compare_exchange(table, next, AcqRel, Acquire, guard)
+                .is_err()
+            {
+                // The previous table is not promoted yet. It'll promote this table too.
+                return;
+            }
+            // SAFETY: `table` is no longer reachable from the map.
+            unsafe { guard.defer_destroy(table) };
+
+            // If the next table was copied before this table was promoted, its promotion has
+            // failed. The read-modify-write synchronizes with the last copy of the next table.
+            table = next;
+            let t = unsafe { table.deref() };
+            if t.copied.fetch_add(0, AcqRel) != t.slots.len() {
+                return;
+            }
+        }
+    }
+
+    /// Moves on from `table` to the next table, after copying the slot of a key and a chunk of
+    /// slots.
+    fn next_table<'g>(
+        &self,
+        table: Shared<'g, Table<K, V>>,
+        slot: Option<&'g Atomic<Entry<K, V>>>,
+        guard: &'g Guard,
+    ) -> Shared<'g, Table<K, V>> {
+        // SAFETY: `table` is protected by `guard`.
+        let t = unsafe { table.deref() };
+        if let Some(slot) = slot {
+            self.copy_slot(t, slot, guard);
+        }
+        self.help_copy(table, guard);
+        t.next.load(Acquire, guard)
+    }
+
+    /// Inserts `new` if its key is absent, and returns the reference to its value. Otherwise,
+    /// returns `new` back with the reference to the present value.
+    #[allow(clippy::type_complexity)]
+    fn insert_entry<'a>(
+        &'a self,
+        mut new: Owned<Entry<K, V>>,
+        guard: &'a Guard,
+    ) -> Result<&'a V, (Owned<Entry<K, V>>, &'a V)> {
+        let hash = new.hash;
+        let mut table = self.table.load(Acquire, guard);
+        loop {
+            // SAFETY: `table` is protected by `guard`.
+            let t = unsafe { table.deref() };
+            let (search, _) = t.search(hash, &new.key, guard);
+            let next = t.next.load(Acquire, guard);
+            let slot = match search {
+                Search::Found(_, entry) if entry.tag() & TOMBSTONE == 0 => {
+                    // SAFETY: `entry` is protected by `guard`.
+                    return Err((new, &unsafe { entry.deref() }.value));
+                }
+                Search::Found(slot, entry) if entry.tag() == TOMBSTONE && next.is_null() => {
+                    match slot.compare_exchange(entry, new, AcqRel, Acquire, guard) {
+                        Ok(inserted) => {
+                            // SAFETY: The deleted entry is replaced.
+                            unsafe { guard.defer_destroy(entry.with_tag(0)) };
+                            let _ = self.count.fetch_add(1, Relaxed);
+                            // SAFETY: `inserted` is protected by `guard`.
+                            return Ok(&unsafe { inserted.deref() }.value);
+                        }
+                        Err(e) => new = e.new,
+                    }
+                    continue;
+                }
+                Search::Vacant(slot, entry) if entry.tag() == 0 && next.is_null() => {
+                    match slot.compare_exchange(entry, new, AcqRel, Acquire, guard) {
+                        Ok(inserted) => {
+                            let _ = self.count.fetch_add(1, Relaxed);
+                            self.claim(t, guard);
+                            // SAFETY: `inserted` is protected by `guard`.
+                            return Ok(&unsafe { inserted.deref() }.value);
+                        }
+                        Err(e) => new = e.new,
+                    }
+                    continue;
+                }
+                Search::Found(slot, _) | Search::Vacant(slot, _) => Some(slot),
+                Search::Full => {
+                    self.start_resize(t, guard);
+                    None
+                }
+            };
+            table = self.next_table(table, slot, guard);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> ConcurrentMap<K, V> for OpenAddressingMap<K, V, S> {
+    fn lookup<'a>(&'a self, key: &K, guard: &'a Guard) -> Option<&'a V> {
+        let hash = self.hash(key);
+        let mut table = self.table.load(Acquire, guard);
+        loop {
+            // SAFETY: `table` is protected by `guard`.
+            let t = unsafe { table.deref() };
+            match t.search(hash, key, guard) {
+                // A frozen live entry is the latest one, as the key is updated in the next table
+                // only after the entry is moved.
+                (Search::Found(_, entry), _) if entry.tag() & TOMBSTONE == 0 => {
+                    return Some(&unsafe { entry.deref() }.value);
+                }
+                (Search::Found(_, entry), _) if entry.tag() == TOMBSTONE => return None,
+                (Search::Vacant(_, entry), false) if entry.tag() == 0 => return None,
+                (Search::Full, _) if t.next.load(Acquire, guard).is_null() => return None,
+                // The key may have been moved or inserted to the next table.
+                _ => {}
+            }
+            table = t.next.load(Acquire, guard);
+        }
+    }
+
+    fn insert(&self, key: K, value: V, guard: &Guard) -> Result<(), V> {
+        let hash = self.hash(&key);
+        let entry = Owned::new(Entry { hash, key, value });
+        self.insert_entry(entry, guard)
+            .map(|_| ())
+            .map_err(|(entry, _)| entry.into_box().value)
+    }
+
+    fn get_or_insert_with<'a, F>(&'a self, key: K, mut f: F, guard: &'a Guard) -> &'a V
+    where
+        K: Clone,
+        F: FnMut() -> V,
+    {
+        if let Some(value) = self.lookup(&key, guard) {
+            return value;
+        }
+        let hash = self.hash(&key);
+        let entry = Owned::new(Entry {
+            hash,
+            key,
+            value: f(),
+        });
+        match self.insert_entry(entry, guard) {
+            Ok(value) | Err((_, value)) => value,
+        }
+    }
+
+    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, ()> {
+        let hash = self.hash(key);
+        let mut table = self.table.load(Acquire, guard);
+        loop {
+            // SAFETY: `table` is protected by `guard`.
+            let t = unsafe { table.deref() };
+            let (search, moved) = t.search(hash, key, guard);
+            let next = t.next.load(Acquire, guard);
+            let slot = match search {
+                Search::Found(slot, entry) if entry.tag() == 0 && next.is_null() => {
+                    match slot.compare_exchange(
+                        entry,
+                        entry.with_tag(TOMBSTONE),
+                        AcqRel,
+                        Acquire,
+                        guard,
+                    ) {
+                        Ok(_) => {
+                            let _ = self.count.fetch_sub(1, Relaxed);
+                            // SAFETY: The entry is retired only after the slot is reused, which
+                            // is protected by `guard`.
+                            return Ok(&unsafe { entry.deref() }.value);
+                        }
+                        Err(_) => continue,
+                    }
+                }
+                Search::Found(_, entry) if entry.tag() == TOMBSTONE => return Err(()),
+                Search::Vacant(_, entry) if entry.tag() == 0 && !moved => return Err(()),
+                Search::Full if next.is_null() => return Err(()),
+                Search::Found(slot, _) | Search::Vacant(slot, _) => Some(slot),
+                Search::Full => None,
+            };
+            table = self.next_table(table, slot, guard);
+        }
+    }
+
+    fn compare_and_replace<'a>(
+        &'a self,
+        key: &K,
+        expected: &V,
+        new: V,
+        guard: &'a Guard,
+    ) -> Result<&'a V, V>
+    where
+        K: Clone,
+        V: PartialEq,
+    {
+        let hash = self.hash(key);
+        let mut new = Owned::new(Entry {
+            hash,
+            key: key.clone(),
+            value: new,
+        });
+        let mut table = self.table.load(Acquire, guard);
+        loop {
+            // SAFETY: `table` is protected by `guard`.
+            let t = unsafe { table.deref() };
+            let (search, moved) = t.search(hash, key, guard);
+            let next = t.next.load(Acquire, guard);
+            let slot = match search {
+                Search::Found(slot, entry) if entry.tag() == 0 && next.is_null() => {
+                    // SAFETY: `entry` is protected by `guard`.
+                    let old = unsafe { entry.deref() };
+                    if old.value != *expected {
+                        return Err(new.into_box().value);
+                    }
+                    match slot.compare_exchange(entry, new, AcqRel, Acquire, guard) {
+                        Ok(_) => {
+                            // SAFETY: The replaced entry is no longer reachable, as in `insert`.
+                            unsafe { guard.defer_destroy(entry) };
+                            return Ok(&old.value);
+                        }
+                        Err(e) => new = e.new,
+                    }
+                    continue;
+                }
+                Search::Found(_, entry) if entry.tag() == TOMBSTONE => {
+                    return Err(new.into_box().value);
+                }
+                Search::Vacant(_, entry) if entry.tag() == 0 && !moved => {
+                    return Err(new.into_box().value);
+                }
+                Search::Full if next.is_null() => return Err(new.into_box().value),
+                Search::Found(slot, _) | Search::Vacant(slot, _) => Some(slot),
+                Search::Full => None,
+            };
+            table = self.next_table(table, slot, guard);
+        }
+    }
+}
+
+impl<K, V, S> Drop for OpenAddressingMap<K, V, S> {
+    fn drop(&mut self) {
+        // SAFETY: We have unique ownership of the tables and the entries via `&mut self`.
+        let guard = unsafe { unprotected() };
+        // An entry being copied may be in the slots of two tables, so collect them first.
+        let mut entries = HashSet::new();
+        let mut table = self.table.load(Relaxed, guard);
+        while !table.is_null() {
+            let t = unsafe { table.into_owned() };
+            for slot in t.slots.iter() {
+                let entry = slot.swap(Shared::null(), Relaxed, guard);
+                if !entry.is_null() {
+                    let _ = entries.insert(entry.with_tag(0).as_raw());
+                }
+            }
+            table = t.next.load(Relaxed, guard);
+        }
+        for entry in entries {
+            drop(unsafe { Owned::from_raw(entry.cast_mut()) });
+        }
+    }
+}
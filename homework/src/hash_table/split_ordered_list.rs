@@ -1,49 +1,292 @@
 //! Split-ordered linked list.
 
+use core::cmp::Ordering::{self, *};
+use core::hash::{BuildHasher, Hash};
 use core::mem::{self, MaybeUninit};
 use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering::*;
+use std::hash::RandomState;
 
-use crossbeam_epoch::{Guard, Owned};
-use cs431::lockfree::list::{Cursor, List, Node};
+use crossbeam_epoch::{Guard, Owned, Shared};
+use cs431::lockfree::list::{self, Cursor, List, Node};
 
 use super::growable_array::GrowableArray;
 use crate::ConcurrentMap;
 
-/// Lock-free map from `usize` in range \[0, 2^63-1\] to `V`.
+/// Lock-free map from `K` to `V`, where the keys are hashed with `S`.
 ///
-/// NOTE: We don't care about hashing in this homework for simplicity.
+/// Items whose hashes collide are distinguished by comparing their keys for equality.
 #[derive(Debug)]
-pub struct SplitOrderedList<V> {
+pub struct SplitOrderedList<K, V, S = RandomState> {
     /// Lock-free list sorted by recursive-split order.
     ///
     /// Use `MaybeUninit::uninit()` when creating sentinel nodes.
-    list: List<usize, MaybeUninit<V>>,
+    list: List<SoKey<K>, MaybeUninit<V>>,
     /// Array of pointers to the buckets.
-    buckets: GrowableArray<Node<usize, MaybeUninit<V>>>,
+    buckets: GrowableArray<Node<SoKey<K>, MaybeUninit<V>>>,
     /// Number of buckets.
     size: AtomicUsize,
     /// Number of items.
     count: AtomicUsize,
+    /// `size` is doubled when `count > size * max_load_factor`.
+    max_load_factor: f64,
+    /// `size` is halved when `count < size * min_load_factor`, but not below `min_size`.
+    min_load_factor: f64,
+    /// Initial number of buckets.
+    min_size: usize,
+    /// The largest `size` since the creation or the last `shrink_to_fit`. Only the buckets below
+    /// this may be initialized.
+    max_size: AtomicUsize,
+    /// Builds the hashers of the keys.
+    hash_builder: S,
 }
 
-impl<V> Default for SplitOrderedList<V> {
-    fn default() -> Self {
-        Self::new()
+/// Key of a node in the list.
+#[derive(Debug)]
+struct SoKey<K> {
+    /// Split-order key, by which the list is sorted.
+    so: usize,
+    /// Key of the item, or `None` for sentinel nodes.
+    key: Option<K>,
+}
+
+impl<K: Eq> SoKey<K> {
+    /// Compares with the search key `(so, key)`, to be used with `Cursor::find_harris_by`.
+    ///
+    /// The items with the same split-order key are not sorted in any order. So a node with the same
+    /// split-order key but a different key is considered less than the search key, so that the
+    /// search scans all of them.
+    fn cmp_search(&self, so: usize, key: Option<&K>) -> Ordering {
+        match self.so.cmp(&so) {
+            Equal if self.key.as_ref() != key => Less,
+            ordering => ordering,
+        }
     }
 }
 
-impl<V> SplitOrderedList<V> {
-    /// `size` is doubled when `count > size * LOAD_FACTOR`.
-    const LOAD_FACTOR: usize = 2;
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Default for SplitOrderedList<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
 
+impl<K: Hash + Eq, V> SplitOrderedList<K, V> {
     /// Creates a new split ordered list.
     pub fn new() -> Self {
-        Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates a new split ordered list that can hold `capacity` items without resizing, with the
+    /// buckets for them initialized.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+
+    /// Creates a new split ordered list that can hold `capacity` items without resizing, with the
+    /// given load factors.
+    ///
+    /// The number of buckets is doubled when the number of items per bucket exceeds
+    /// `max_load_factor`, and halved when it falls below `min_load_factor`. Shrinking only makes
+    /// the buckets unused; call [`shrink_to_fit`](Self::shrink_to_fit) to free them. The number of
+    /// buckets never falls below the initial one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_load_factor` is not positive, or `min_load_factor` is not in
+    /// `[0, max_load_factor / 2)` so that the table would not shrink right after growing.
+    pub fn with_capacity_and_load_factors(
+        capacity: usize,
+        max_load_factor: f64,
+        min_load_factor: f64,
+    ) -> Self {
+        Self::with_config(
+            capacity,
+            max_load_factor,
+            min_load_factor,
+            RandomState::new(),
+        )
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> SplitOrderedList<K, V, S> {
+    /// The default max load factor.
+    const MAX_LOAD_FACTOR: f64 = 2.0;
+
+    /// The default min load factor.
+    const MIN_LOAD_FACTOR: f64 = 0.25;
+
+    /// Creates a new split ordered list which uses the given hash builder to hash keys.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Creates a new split ordered list that can hold `capacity` items without resizing, which
+    /// uses the given hash builder to hash keys.
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self::with_config(
+            capacity,
+            Self::MAX_LOAD_FACTOR,
+            Self::MIN_LOAD_FACTOR,
+            hash_builder,
+        )
+    }
+
+    /// See [`with_capacity_and_load_factors`](SplitOrderedList::with_capacity_and_load_factors).
+    fn with_config(
+        capacity: usize,
+        max_load_factor: f64,
+        min_load_factor: f64,
+        hash_builder: S,
+    ) -> Self {
+        assert!(max_load_factor > 0.0, "max load factor should be positive");
+        assert!(
+            (0.0..max_load_factor / 2.0).contains(&min_load_factor),
+            "min load factor should be in [0, max_load_factor / 2)"
+        );
+
+        let size = ((capacity as f64 / max_load_factor).ceil() as usize)
+            .next_power_of_two()
+            .max(2);
+        let result = Self {
             list: List::new(),
             buckets: GrowableArray::new(),
-            size: AtomicUsize::new(2),
+            size: AtomicUsize::new(size),
             count: AtomicUsize::new(0),
+            max_load_factor,
+            min_load_factor,
+            min_size: size,
+            max_size: AtomicUsize::new(size),
+            hash_builder,
+        };
+
+        if capacity > 0 {
+            let guard = crossbeam_epoch::pin();
+            for index in 0..size {
+                let _ = result.lookup_bucket(index, &guard);
+            }
+        }
+        result
+    }
+
+    /// Returns the number of items the list can hold without resizing.
+    pub fn capacity(&self) -> usize {
+        (self.size.load(Relaxed) as f64 * self.max_load_factor) as usize
+    }
+
+    /// Returns the number of items in the list.
+    ///
+    /// The concurrent insertions and deletions may or may not be counted.
+    pub fn len(&self) -> usize {
+        self.count.load(Relaxed)
+    }
+
+    /// Returns `true` if the list contains no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the items in the list, in split order.
+    ///
+    /// The iteration is not a snapshot: it may or may not see the concurrent insertions and
+    /// deletions.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, K, V> {
+        Iter {
+            inner: self.list.iter(guard),
+        }
+    }
+
+    /// Deletes all items in the list.
+    ///
+    /// Each item is deleted as if by `delete`, so this can be called concurrently with the other
+    /// operations. The items inserted concurrently may not be deleted.
+    pub fn clear(&self, guard: &Guard) {
+        for (key, _) in self.iter(guard) {
+            let _ = self.delete(key, guard);
+        }
+    }
+
+    /// Frees the buckets that are unused since the list has shrunk.
+    ///
+    /// The sentinel nodes of the unused buckets are removed from the list, so that the items in
+    /// them belong to their parent buckets. Then the segments of the bucket array for them are
+    /// freed.
+    ///
+    /// This requires exclusive access, as the other threads may start their searches from the
+    /// sentinels being removed. So a list that is shared for its whole lifetime keeps the buckets
+    /// for its peak size, though the number of buckets in use shrinks.
+    pub fn shrink_to_fit(&mut self) {
+        let size = *self.size.get_mut();
+        let max_size = mem::replace(self.max_size.get_mut(), size);
+        let guard = crossbeam_epoch::pin();
+
+        // Remove the children before their parents, so that we can search for a sentinel from its
+        // parent's.
+        for index in (size..max_size).rev() {
+            let bucket = self.buckets.get(index, &guard);
+            let sentinel = bucket.load(Relaxed, &guard);
+            if sentinel.is_null() {
+                continue;
+            }
+            // SAFETY: Sentinels are removed only here, with exclusive access.
+            let so = unsafe { sentinel.deref() }.key().so;
+
+            // The parent of a bucket is the bucket with the most significant bit unset. It
+            // precedes its children in the list, and is initialized before them.
+            let parent = index & !(1 << index.ilog2());
+            let parent_bucket = self.buckets.get(parent, &guard);
+            let parent_sentinel = parent_bucket.load(Relaxed, &guard);
+            let mut cursor = if parent_sentinel.is_null() {
+                self.list.head(&guard)
+            } else {
+                Cursor::new(parent_bucket, parent_sentinel)
+            };
+            // With exclusive access, the search doesn't fail, and the sentinel is found.
+            assert_eq!(
+                cursor.find_harris_by(|key| key.cmp_search(so, None), &guard),
+                Ok(true)
+            );
+            assert!(cursor.delete(&guard).is_ok());
+            bucket.store(Shared::null(), Relaxed);
+        }
+
+        // Now the unused buckets are all null, so give back their segments.
+        self.buckets.shrink_to(size, &guard);
+    }
+
+    /// Resizes the list for `count` items, if `size` is still the number of buckets.
+    ///
+    /// This should be called after an insertion or a deletion, with the number of buckets the
+    /// operation has seen and the number of items after the operation.
+    fn resize(&self, size: usize, count: usize) {
+        let new_size = if count as f64 > size as f64 * self.max_load_factor {
+            size * 2
+        } else if size > self.min_size && (count as f64) < size as f64 * self.min_load_factor {
+            size / 2
+        } else {
+            return;
+        };
+
+        if self
+            .size
+            .compare_exchange(size, new_size, Release, Relaxed)
+            .is_ok()
+        {
+            let _ = self.max_size.fetch_max(new_size, Relaxed);
+        }
+    }
+
+    /// Hashes the key into the range \[0, 2^63-1\], i.e. with the most significant bit unset.
+    fn hash(&self, key: &K) -> usize {
+        self.hash_builder.hash_one(key) as usize & (usize::MAX >> 1)
+    }
+
+    /// Creates the key of the regular node for the given key.
+    fn regular_key(&self, key: K) -> SoKey<K> {
+        // The most significant bit is set so that the key is distinct from the sentinel keys, and
+        // comes after the sentinel key of its bucket.
+        SoKey {
+            so: (self.hash(&key) | (1 << (usize::BITS - 1))).reverse_bits(),
+            key: Some(key),
         }
     }
 
@@ -53,7 +296,7 @@ impl<V> SplitOrderedList<V> {
         &'s self,
         index: usize,
         guard: &'s Guard,
-    ) -> Cursor<'s, usize, MaybeUninit<V>> {
+    ) -> Cursor<'s, SoKey<K>, MaybeUninit<V>> {
         todo!()
     }
 
@@ -61,33 +304,138 @@ impl<V> SplitOrderedList<V> {
     /// Returns `(size, found, cursor)`
     fn find<'s>(
         &'s self,
-        key: &usize,
+        key: &K,
         guard: &'s Guard,
-    ) -> (usize, bool, Cursor<'s, usize, MaybeUninit<V>>) {
+    ) -> (usize, bool, Cursor<'s, SoKey<K>, MaybeUninit<V>>) {
         todo!()
     }
+}
 
-    fn assert_valid_key(key: usize) {
-        assert!(key.leading_zeros() != 0);
+impl<K: Hash + Eq, V, S: BuildHasher> ConcurrentMap<K, V> for SplitOrderedList<K, V, S> {
+    fn lookup<'a>(&'a self, key: &K, guard: &'a Guard) -> Option<&'a V> {
+        todo!()
     }
-}
 
-impl<V> ConcurrentMap<usize, V> for SplitOrderedList<V> {
-    fn lookup<'a>(&'a self, key: &usize, guard: &'a Guard) -> Option<&'a V> {
-        Self::assert_valid_key(*key);
+    fn insert(&self, key: K, value: V, guard: &Guard) -> Result<(), V> {
+        todo!()
+    }
 
+    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, ()> {
         todo!()
     }
 
-    fn insert(&self, key: usize, value: V, guard: &Guard) -> Result<(), V> {
-        Self::assert_valid_key(key);
+    fn get_or_insert_with<'a, F>(&'a self, key: K, mut f: F, guard: &'a Guard) -> &'a V
+    where
+        K: Clone,
+        F: FnMut() -> V,
+    {
+        // SAFETY: In this and the following methods, the values of the regular nodes, i.e. the
+        // nodes found by `find` and the nodes we create, are initialized.
+        let (_, found, cursor) = self.find(&key, guard);
+        if found {
+            return unsafe { cursor.lookup().assume_init_ref() };
+        }
 
-        todo!()
+        let mut node = Owned::new(Node::new(
+            self.regular_key(key.clone()),
+            MaybeUninit::new(f()),
+        ));
+        loop {
+            let (size, found, mut cursor) = self.find(&key, guard);
+            if found {
+                drop(unsafe { node.into_box().into_value().assume_init() });
+                return unsafe { cursor.lookup().assume_init_ref() };
+            }
+            match cursor.insert(node, guard) {
+                Ok(()) => {
+                    let count = self.count.fetch_add(1, Relaxed) + 1;
+                    self.resize(size, count);
+                    return unsafe { cursor.lookup().assume_init_ref() };
+                }
+                Err(n) => node = n,
+            }
+        }
     }
 
-    fn delete<'a>(&'a self, key: &usize, guard: &'a Guard) -> Result<&'a V, ()> {
-        Self::assert_valid_key(*key);
+    fn upsert<'a, F>(&'a self, key: K, mut f: F, guard: &'a Guard) -> Option<&'a V>
+    where
+        K: Clone,
+        F: FnMut(Option<&V>) -> V,
+    {
+        let (mut size, mut found, mut cursor) = self.find(&key, guard);
+        let old = found.then(|| unsafe { cursor.lookup().assume_init_ref() });
+        let mut node = Owned::new(Node::new(
+            self.regular_key(key.clone()),
+            MaybeUninit::new(f(old)),
+        ));
+        loop {
+            if found {
+                match cursor.replace(node, guard) {
+                    Ok(old) => return Some(unsafe { old.assume_init_ref() }),
+                    Err(n) => node = n,
+                }
+            } else {
+                match cursor.insert(node, guard) {
+                    Ok(()) => {
+                        let count = self.count.fetch_add(1, Relaxed) + 1;
+                        self.resize(size, count);
+                        return None;
+                    }
+                    Err(n) => node = n,
+                }
+            }
 
-        todo!()
+            (size, found, cursor) = self.find(&key, guard);
+            let old = found.then(|| unsafe { cursor.lookup().assume_init_ref() });
+            let value = node.value_mut();
+            unsafe { value.assume_init_drop() };
+            let _ = value.write(f(old));
+        }
+    }
+
+    fn compare_and_replace<'a>(
+        &'a self,
+        key: &K,
+        expected: &V,
+        new: V,
+        guard: &'a Guard,
+    ) -> Result<&'a V, V>
+    where
+        K: Clone,
+        V: PartialEq,
+    {
+        // SAFETY: See `get_or_insert_with`.
+        let mut node = Owned::new(Node::new(
+            self.regular_key(key.clone()),
+            MaybeUninit::new(new),
+        ));
+        loop {
+            let (_, found, mut cursor) = self.find(key, guard);
+            if !found || unsafe { cursor.lookup().assume_init_ref() } != expected {
+                return Err(unsafe { node.into_box().into_value().assume_init() });
+            }
+            match cursor.replace(node, guard) {
+                Ok(old) => return Ok(unsafe { old.assume_init_ref() }),
+                Err(n) => node = n,
+            }
+        }
+    }
+}
+
+/// Iterator over the items of a [`SplitOrderedList`], created by [`SplitOrderedList::iter`].
+#[derive(Debug)]
+pub struct Iter<'g, K, V> {
+    inner: list::Iter<'g, SoKey<K>, MaybeUninit<V>>,
+}
+
+impl<'g, K, V> Iterator for Iter<'g, K, V> {
+    type Item = (&'g K, &'g V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.find_map(|(key, value)| {
+            // SAFETY: Only the sentinel nodes have uninitialized values, and they have no keys.
+            let key = key.key.as_ref()?;
+            Some((key, unsafe { value.assume_init_ref() }))
+        })
     }
 }
@@ -0,0 +1,231 @@
+use core::cell::Cell;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU64, Ordering, fence};
+use std::sync::Mutex;
+
+use super::ERAS;
+use super::retire::Retired;
+use crate::hazard_pointer::{self, HazardBag};
+
+/// The era reserved by no shield. The era clock starts from 1.
+const NONE: u64 = 0;
+
+/// Represents the ownership of an era slot.
+///
+/// The slots are the hazard pointer slots of [`HazardBag`], and a reserved era is stored as the
+/// address of the hazard. So the slots are acquired, recycled and scanned as in hazard pointers.
+#[derive(Debug)]
+pub struct Shield<'s> {
+    inner: hazard_pointer::Shield,
+    /// The era in the slot of `inner`. Only this shield writes to the slot, so this is exact.
+    era: Cell<u64>,
+    eras: &'s EraBag,
+}
+
+impl<'s> Shield<'s> {
+    /// Creates a new shield for hazard eras.
+    pub fn new(eras: &'s EraBag) -> Self {
+        Self {
+            inner: hazard_pointer::Shield::new(&eras.slots),
+            era: Cell::new(NONE),
+            eras,
+        }
+    }
+
+    /// Stores `era` to the slot, and makes it visible to the `collect`s that start afterwards.
+    fn store(&self, era: u64) {
+        self.era.set(era);
+        self.inner
+            .set(ptr::without_provenance_mut::<()>(era as usize));
+        // Pairs with the fences in `RetiredSet`. `inner.set` is not required to issue one.
+        fence(Ordering::SeqCst);
+    }
+
+    /// Reserves the current era, so that all objects that are not retired yet are protected.
+    pub fn reserve(&self) {
+        let era = self.eras.clock.load(Ordering::Acquire);
+        if self.era.get() != era {
+            self.store(era);
+        }
+    }
+
+    /// Clear the reserved era.
+    pub fn clear(&self) {
+        self.era.set(NONE);
+        self.inner.clear();
+    }
+
+    /// Check if `src` still points to `pointer`. If not, returns the current value.
+    ///
+    /// For a pointer `p` loaded before `reserve()`, if "`src` still pointing to `p`" implies that
+    /// `p` is not retired, then `Ok(())` means that `p` is protected by the reserved era.
+    pub fn validate<T>(pointer: *mut T, src: &AtomicPtr<T>) -> Result<(), *mut T> {
+        hazard_pointer::Shield::validate(pointer, src)
+    }
+
+    /// Get a protected pointer from `src`.
+    ///
+    /// Unlike hazard pointers, this issues a fence only if the era has advanced since the last
+    /// reservation.
+    pub fn protect<T>(&self, src: &AtomicPtr<T>) -> *mut T {
+        loop {
+            // If the era is unchanged after loading `pointer`, then `pointer` was born no later
+            // than `era`, and is retired no earlier than `era` as it was reachable after `era` was
+            // reserved.
+            let pointer = src.load(Ordering::Acquire);
+            let current = self.eras.clock.load(Ordering::Acquire);
+            if current == self.era.get() {
+                return pointer;
+            }
+            self.store(current);
+        }
+    }
+}
+
+impl Default for Shield<'static> {
+    fn default() -> Self {
+        Self::new(&ERAS)
+    }
+}
+
+/// Global bag of hazard eras, with the era clock.
+#[derive(Debug)]
+pub struct EraBag {
+    clock: AtomicU64,
+    /// The slots of the reserved eras.
+    slots: HazardBag,
+    /// Retired pointers left by exited threads, to be reclaimed by the other threads.
+    pub(super) orphans: Mutex<Vec<Retired>>,
+}
+
+/// An object allocated by [`EraBag::alloc`], with the era in which it is allocated.
+struct Block<T> {
+    birth: u64,
+    data: T,
+}
+
+impl<T> Block<T> {
+    /// Returns the block containing `data`.
+    ///
+    /// # Safety
+    ///
+    /// `data` must be allocated by [`EraBag::alloc`].
+    unsafe fn from_data(data: *mut T) -> *mut Self {
+        unsafe { data.byte_sub(core::mem::offset_of!(Self, data)) }.cast()
+    }
+}
+
+impl EraBag {
+    /// Creates a new global era bag.
+    pub const fn new() -> Self {
+        Self {
+            clock: AtomicU64::new(NONE + 1),
+            slots: HazardBag::new(),
+            orphans: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the current era.
+    pub fn era(&self) -> u64 {
+        self.clock.load(Ordering::Acquire)
+    }
+
+    /// Advances the era clock.
+    pub(super) fn advance(&self) {
+        let _ = self.clock.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Allocates `value` on the heap, recording the current era as its birth era.
+    pub fn alloc<T>(&self, value: T) -> *mut T {
+        let block = Box::into_raw(Box::new(Block {
+            birth: self.era(),
+            data: value,
+        }));
+        // SAFETY: `block` is valid.
+        unsafe { &raw mut (*block).data }
+    }
+
+    /// Returns all the reserved eras in the bag, sorted and deduplicated.
+    pub(super) fn reserved_eras(&self) -> Vec<u64> {
+        // The hazards are sorted by their addresses, i.e. by the eras.
+        self.slots
+            .sorted_hazards()
+            .into_iter()
+            .map(|era| era.addr() as u64)
+            .filter(|&era| era != NONE)
+            .collect()
+    }
+}
+
+impl Default for EraBag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for EraBag {
+    /// Frees all orphaned pointers. This is safe as there are no `Shield`s of the bag.
+    fn drop(&mut self) {
+        for retired in self.orphans.get_mut().unwrap().drain(..) {
+            unsafe { retired.free() };
+        }
+    }
+}
+
+/// Frees a pointer allocated by [`EraBag::alloc`] immediately.
+///
+/// # Safety
+///
+/// `pointer` must be allocated by [`EraBag::alloc`], and one must have unique ownership to it.
+pub unsafe fn free<T>(pointer: *mut T) {
+    drop(unsafe { Box::from_raw(Block::from_data(pointer)) });
+}
+
+/// Returns the birth era of a pointer allocated by [`EraBag::alloc`].
+///
+/// # Safety
+///
+/// `pointer` must be allocated by [`EraBag::alloc`], and must be valid.
+pub(super) unsafe fn birth<T>(pointer: *mut T) -> u64 {
+    unsafe { (*Block::from_data(pointer)).birth }
+}
+
+#[cfg(all(test, not(feature = "check-loom")))]
+mod tests {
+    use std::sync::atomic::AtomicPtr;
+
+    use super::{EraBag, Shield, birth, free};
+
+    // `protect` should reserve the current era, and dropping the shield should clear it.
+    #[test]
+    fn protect_reserves_era() {
+        let eras = EraBag::new();
+        let pointer = eras.alloc(42usize);
+        assert_eq!(unsafe { birth(pointer) }, eras.era());
+
+        let shield = Shield::new(&eras);
+        assert!(eras.reserved_eras().is_empty());
+        assert_eq!(shield.protect(&AtomicPtr::new(pointer)), pointer);
+        assert_eq!(eras.reserved_eras(), vec![eras.era()]);
+
+        eras.advance();
+        let _ = shield.protect(&AtomicPtr::new(pointer));
+        assert_eq!(eras.reserved_eras(), vec![eras.era()]);
+
+        drop(shield);
+        assert!(eras.reserved_eras().is_empty());
+        unsafe { free(pointer) };
+    }
+
+    // Slots of dropped shields should be recycled.
+    #[test]
+    fn recycle_slots() {
+        let eras = EraBag::new();
+        let shield1 = Shield::new(&eras);
+        drop(shield1);
+        let shield2 = Shield::new(&eras);
+        assert_eq!(eras.slots.stats().slots_allocated, 1);
+        let shield3 = Shield::new(&eras);
+        assert_eq!(eras.slots.stats().slots_allocated, 2);
+    }
+}
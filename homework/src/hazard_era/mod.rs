@@ -0,0 +1,133 @@
+//! Hazard eras.
+//!
+//! Hazard pointers protect each pointer individually, so that a fence is required whenever a new
+//! pointer is protected. Hazard eras instead protect all objects that are alive during an *era*. A
+//! global era clock is advanced as objects are retired, and each object records the era in which it
+//! is allocated (its birth era) and retired (its retire era). A shield reserves the current era,
+//! and a retired object may be freed if no reserved era is between its birth era and retire era. A
+//! shield needs to be refenced only when the era changes, and a stalled thread only prevents the
+//! reclamation of the objects alive in the era it reserved.
+//!
+//! Hence objects must be allocated with [`alloc`] so that they record their birth eras.
+//!
+//! The eras are reserved in the slots of [`HazardBag`](crate::hazard_pointer::HazardBag), so this
+//! relies on the hazard pointer homework.
+//!
+//! Ramalhete and Correia. Brief Announcement: Hazard Eras - Non-Blocking Memory Reclamation. SPAA
+//! 2017. <https://doi.org/10.1145/3087556.3087588>
+//!
+//! # Example
+//!
+//! ```
+//! use std::ptr;
+//! use std::sync::atomic::{AtomicPtr, Ordering};
+//! use cs431_homework::hazard_era::{alloc, collect, retire, Shield};
+//!
+//! let shield = Shield::default();
+//! let atomic = AtomicPtr::new(alloc(1usize));
+//! let protected = shield.protect(&atomic);
+//! assert_eq!(unsafe { *protected }, 1);
+//!
+//! // unlink the block and retire
+//! atomic.store(ptr::null_mut(), Ordering::Relaxed);
+//! unsafe { retire(protected); }
+//!
+//! // manually trigger reclamation (not necessary)
+//! collect();
+//! ```
+
+use core::cell::RefCell;
+use core::sync::atomic::AtomicPtr;
+use std::thread_local;
+
+use cs431::lockfree::smr::Smr;
+
+mod era;
+mod retire;
+
+pub use era::{EraBag, Shield, free};
+pub use retire::RetiredSet;
+
+/// Default global bag of all hazard eras.
+pub static ERAS: EraBag = EraBag::new();
+
+thread_local! {
+    /// Default thread-local retired pointer list.
+    static RETIRED: RefCell<RetiredSet<'static>> = RefCell::new(RetiredSet::default());
+}
+
+/// Allocates `value` on the heap, recording the current era of the default global [`ERAS`] as its
+/// birth era.
+pub fn alloc<T>(value: T) -> *mut T {
+    ERAS.alloc(value)
+}
+
+/// Retires a pointer.
+///
+/// # Safety
+///
+/// * `pointer` must be removed from shared memory before calling this function, and must be
+///   allocated by [`alloc`].
+/// * The same `pointer` should only be retired once.
+/// * It should be safe to drop `*pointer` from any thread, e.g. `T: Send`.
+pub unsafe fn retire<T>(pointer: *mut T) {
+    RETIRED.with(|r| unsafe { r.borrow_mut().retire(pointer) });
+}
+
+/// Frees the pointers that are `retire`d by the current thread and not protected by the eras
+/// reserved by any other threads.
+pub fn collect() {
+    RETIRED.with(|r| r.borrow_mut().collect());
+}
+
+/// Hazard era based reclamation for the data structures in [`cs431::lockfree`], using the default
+/// global [`ERAS`] and the default thread-local retired pointer list.
+///
+/// # Example
+///
+/// ```
+/// use cs431::lockfree::Queue;
+/// use cs431_homework::hazard_era::HazardEra;
+///
+/// let queue = Queue::<_, HazardEra>::default();
+/// queue.push(1, &mut ());
+/// assert_eq!(queue.try_pop(&mut ()), Some(1));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HazardEra;
+
+unsafe impl Smr for HazardEra {
+    type Guard = ();
+
+    type Shield = Shield<'static>;
+
+    fn pin() {}
+
+    fn shield(_guard: &()) -> Shield<'static> {
+        Shield::default()
+    }
+
+    fn set<T>(shield: &Shield<'static>, _pointer: *mut T) {
+        shield.reserve()
+    }
+
+    fn validate<T>(pointer: *mut T, src: &AtomicPtr<T>) -> Result<(), *mut T> {
+        Shield::validate(pointer, src)
+    }
+
+    fn protect<T>(shield: &Shield<'static>, src: &AtomicPtr<T>) -> *mut T {
+        shield.protect(src)
+    }
+
+    fn alloc<T>(value: T) -> *mut T {
+        alloc(value)
+    }
+
+    unsafe fn free<T>(pointer: *mut T) {
+        unsafe { free(pointer) }
+    }
+
+    unsafe fn retire<T>(_guard: &(), pointer: *mut T) {
+        unsafe { retire(pointer) }
+    }
+}
@@ -0,0 +1,207 @@
+use core::marker::PhantomData;
+use core::mem;
+use core::sync::atomic::{Ordering, fence};
+
+use super::ERAS;
+use super::era::{self, EraBag};
+
+/// A retired pointer, with the eras in which it is allocated and retired.
+#[derive(Debug)]
+pub(super) struct Retired {
+    /// The machine representation of the pointer.
+    pointer: *mut (),
+    /// The deleter of the object, i.e. `free::<T>` where `T` is the type of the object.
+    deleter: unsafe fn(*mut ()),
+    birth: u64,
+    retire: u64,
+}
+
+// SAFETY: Retired pointers are only sent to other threads to be freed, when the retiring thread
+// exits.
+unsafe impl Send for Retired {}
+
+impl Retired {
+    /// Frees the retired pointer.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must not be protected by any era, and must be freed only once.
+    pub(super) unsafe fn free(&self) {
+        unsafe { (self.deleter)(self.pointer) }
+    }
+
+    /// Returns `true` if the pointer is alive in any of the sorted `eras`.
+    fn is_protected(&self, eras: &[u64]) -> bool {
+        // The first reserved era no earlier than the birth era.
+        let i = eras.partition_point(|&era| era < self.birth);
+        eras.get(i).is_some_and(|&era| era <= self.retire)
+    }
+}
+
+/// Thread-local list of retired pointers.
+#[derive(Debug)]
+pub struct RetiredSet<'s> {
+    eras: &'s EraBag,
+    inner: Vec<Retired>,
+    /// The number of pointers retired since this thread last advanced the era clock.
+    retires: usize,
+    _marker: PhantomData<*const ()>, // !Send + !Sync
+}
+
+impl<'s> RetiredSet<'s> {
+    /// The max length of retired pointer list. `collect` is triggered when `THRESHOLD` pointers
+    /// are retired.
+    pub const THRESHOLD: usize = 64;
+
+    /// Each thread advances the era clock every `ERA_FREQUENCY` retirements.
+    ///
+    /// Advancing the clock more often bounds the number of unreclaimed objects more tightly, at the
+    /// cost of more fences in `Shield::protect`.
+    pub const ERA_FREQUENCY: usize = 16;
+
+    /// Create a new retired pointer list protected by the given `EraBag`.
+    pub fn new(eras: &'s EraBag) -> Self {
+        Self {
+            eras,
+            inner: Vec::new(),
+            retires: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Retires a pointer.
+    ///
+    /// # Safety
+    ///
+    /// * `pointer` must be removed from shared memory before calling this function, and must be
+    ///   allocated by [`EraBag::alloc`] of the bag of this list.
+    /// * The same `pointer` should only be retired once.
+    /// * It should be safe to drop `*pointer` from any thread, e.g. `T: Send`, as the pointers
+    ///   still protected when this list is dropped are freed by the other threads.
+    pub unsafe fn retire<T>(&mut self, pointer: *mut T) {
+        unsafe fn free<T>(data: *mut ()) {
+            unsafe { era::free(data.cast::<T>()) }
+        }
+
+        // The retire era should not be older than the eras reserved by the shields that validated
+        // `pointer` before it was unlinked. This fence pairs with the fences in `Shield`.
+        fence(Ordering::SeqCst);
+        let retired = Retired {
+            pointer: pointer.cast(),
+            deleter: free::<T>,
+            birth: unsafe { era::birth(pointer) },
+            retire: self.eras.era(),
+        };
+        self.inner.push(retired);
+
+        self.retires += 1;
+        if self.retires == Self::ERA_FREQUENCY {
+            self.retires = 0;
+            self.eras.advance();
+        }
+        if self.inner.len() >= Self::THRESHOLD {
+            self.collect();
+        }
+    }
+
+    /// Free the pointers that are `retire`d by the current thread and not alive in the eras
+    /// reserved by any other threads.
+    ///
+    /// Also adopts the pointers orphaned by exited threads, so that they are freed here if not
+    /// protected.
+    pub fn collect(&mut self) {
+        if let Ok(mut orphans) = self.eras.orphans.try_lock() {
+            self.inner.append(&mut orphans);
+        }
+
+        // Pairs with the fences in `Shield`, so that the eras reserved before the retirement are
+        // visible.
+        fence(Ordering::SeqCst);
+        let eras = self.eras.reserved_eras();
+        self.inner.retain(|retired| {
+            if retired.is_protected(&eras) {
+                return true;
+            }
+            // SAFETY: `retired` is not alive in any reserved era, so no other thread can access
+            // it.
+            unsafe { retired.free() };
+            false
+        });
+    }
+}
+
+impl Default for RetiredSet<'static> {
+    fn default() -> Self {
+        Self::new(&ERAS)
+    }
+}
+
+impl Drop for RetiredSet<'_> {
+    fn drop(&mut self) {
+        // Try to free the remaining local retired pointers. Those that are still protected are
+        // moved to the orphans of the bag, which are then reclaimed by the other threads.
+        self.collect();
+        if !self.inner.is_empty() {
+            self.eras
+                .orphans
+                .lock()
+                .unwrap()
+                .append(&mut mem::take(&mut self.inner));
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "check-loom")))]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+    use std::sync::atomic::AtomicPtr;
+
+    use super::{EraBag, RetiredSet};
+    use crate::hazard_era::Shield;
+
+    struct Tester(Rc<RefCell<HashSet<usize>>>, usize);
+    impl Drop for Tester {
+        fn drop(&mut self) {
+            let _ = self.0.borrow_mut().insert(self.1);
+        }
+    }
+
+    // retire `THRESHOLD` pointers to trigger collection
+    #[test]
+    fn retire_threshold_collect() {
+        let eras = EraBag::new();
+        let mut retires = RetiredSet::new(&eras);
+        let freed = Rc::new(RefCell::new(HashSet::new()));
+        for i in 0..RetiredSet::THRESHOLD {
+            unsafe { retires.retire(eras.alloc(Tester(freed.clone(), i))) };
+        }
+        let freed = Rc::try_unwrap(freed).unwrap().into_inner();
+
+        assert_eq!(freed, (0..RetiredSet::THRESHOLD).collect())
+    }
+
+    // A reserved era should protect the pointers alive in it, but not those born after it.
+    #[test]
+    fn reserved_era() {
+        let eras = EraBag::new();
+        let mut retires = RetiredSet::new(&eras);
+        let freed = Rc::new(RefCell::new(HashSet::new()));
+
+        let old = eras.alloc(Tester(freed.clone(), 0));
+        let shield = Shield::new(&eras);
+        let _ = shield.protect(&AtomicPtr::new(old));
+        eras.advance();
+        let new = eras.alloc(Tester(freed.clone(), 1));
+
+        unsafe { retires.retire(old) };
+        unsafe { retires.retire(new) };
+        retires.collect();
+        assert_eq!(*freed.borrow(), HashSet::from([1]));
+
+        drop(shield);
+        retires.collect();
+        assert_eq!(*freed.borrow(), HashSet::from([0, 1]));
+    }
+}
@@ -0,0 +1,274 @@
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+#[cfg(not(feature = "check-loom"))]
+use core::sync::atomic::{AtomicPtr, Ordering};
+use core::{fmt, ptr};
+
+#[cfg(feature = "check-loom")]
+use loom::sync::atomic::{AtomicPtr, Ordering};
+
+use super::{HAZARDS, Shield, retire};
+
+/// An atomic pointer to an object that is reclaimed with hazard pointers, using the default global
+/// [`HAZARDS`] and the default thread-local retired pointer list.
+///
+/// The object is owned by the pointer: it is retired when it is replaced by [`store`] or
+/// [`compare_exchange`], and freed when the pointer is dropped.
+///
+/// [`store`]: HpAtomic::store
+/// [`compare_exchange`]: HpAtomic::compare_exchange
+///
+/// # Example
+///
+/// ```
+/// use cs431_homework::hazard_pointer::{HpAtomic, HpOwned, Shield};
+///
+/// let atomic = HpAtomic::new(1usize);
+/// let mut shield = Shield::default();
+/// loop {
+///     let current = atomic.load(&mut shield);
+///     let new = HpOwned::new(current.as_ref().unwrap() + 1);
+///     if atomic.compare_exchange(current, Some(new)).is_ok() {
+///         break;
+///     }
+/// }
+/// assert_eq!(atomic.load(&mut shield).as_ref(), Some(&2));
+/// ```
+pub struct HpAtomic<T> {
+    inner: AtomicPtr<T>,
+    _marker: PhantomData<Box<T>>,
+}
+
+// The object may be accessed by multiple threads, and freed by any thread.
+unsafe impl<T: Send + Sync> Send for HpAtomic<T> {}
+unsafe impl<T: Send + Sync> Sync for HpAtomic<T> {}
+
+/// An owned heap-allocated object that can be stored in an [`HpAtomic`].
+pub struct HpOwned<T> {
+    inner: Box<T>,
+}
+
+/// A pointer loaded from an [`HpAtomic`], protected by a shield for the lifetime `'s`.
+pub struct Protected<'s, T> {
+    pointer: *mut T,
+    _marker: PhantomData<&'s T>,
+}
+
+impl<T> HpAtomic<T> {
+    /// Creates a new atomic pointer to `value`.
+    pub fn new(value: T) -> Self {
+        Self::from(HpOwned::new(value))
+    }
+
+    /// Creates a new null atomic pointer.
+    pub fn null() -> Self {
+        Self {
+            inner: AtomicPtr::new(ptr::null_mut()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Loads the pointer, protecting it with `shield`.
+    ///
+    /// The shield is mutably borrowed so that it protects only one pointer at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shield` does not belong to the default global [`HAZARDS`], as the objects are
+    /// retired to the default retired pointer list that only checks its hazards.
+    pub fn load<'s>(&'s self, shield: &'s mut Shield) -> Protected<'s, T> {
+        assert!(
+            shield.is_in(&HAZARDS),
+            "`HpAtomic` should be protected by the shields of `HAZARDS`"
+        );
+        Protected {
+            pointer: shield.protect(&self.inner),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Takes the object out of the pointer.
+    pub fn into_owned(self) -> Option<HpOwned<T>> {
+        let pointer = self.inner.swap(ptr::null_mut(), Ordering::Relaxed);
+        // SAFETY: We have unique ownership of the object via `self`.
+        unsafe { HpOwned::from_raw(pointer) }
+    }
+}
+
+// The retired objects are dropped later, possibly by another thread. So they should be sendable and
+// should not borrow anything that may be gone by then, as for `crossbeam_epoch::Guard::defer`.
+impl<T: Send + 'static> HpAtomic<T> {
+    /// Stores `new` to the pointer, retiring the previous object.
+    ///
+    /// The object should not borrow anything, as it may be dropped after the borrow ends:
+    ///
+    /// ```compile_fail
+    /// use cs431_homework::hazard_pointer::{HpAtomic, HpOwned};
+    ///
+    /// let value = 1usize;
+    /// let atomic = HpAtomic::new(&value);
+    /// atomic.store(None);
+    /// ```
+    pub fn store(&self, new: Option<HpOwned<T>>) {
+        let old = self.inner.swap(HpOwned::into_raw(new), Ordering::AcqRel);
+        if !old.is_null() {
+            // SAFETY: `old` is unlinked by the above swap, and only the thread that unlinked it
+            // retires it.
+            unsafe { retire(old) };
+        }
+    }
+
+    /// Stores `new` to the pointer if it is `current`, retiring `current` on success.
+    ///
+    /// On failure, returns `new` back.
+    pub fn compare_exchange(
+        &self,
+        current: Protected<'_, T>,
+        new: Option<HpOwned<T>>,
+    ) -> Result<(), Option<HpOwned<T>>> {
+        let new_ptr = HpOwned::into_raw(new);
+        match self.inner.compare_exchange(
+            current.pointer,
+            new_ptr,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(old) => {
+                if !old.is_null() {
+                    // SAFETY: `old` is unlinked by the above CAS, and only the thread that unlinked
+                    // it retires it.
+                    unsafe { retire(old) };
+                }
+                Ok(())
+            }
+            // SAFETY: `new_ptr` is not shared with other threads.
+            Err(_) => Err(unsafe { HpOwned::from_raw(new_ptr) }),
+        }
+    }
+}
+
+impl<T> Default for HpAtomic<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T> From<HpOwned<T>> for HpAtomic<T> {
+    fn from(owned: HpOwned<T>) -> Self {
+        Self {
+            inner: AtomicPtr::new(HpOwned::into_raw(Some(owned))),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for HpAtomic<T> {
+    fn drop(&mut self) {
+        let pointer = self.inner.load(Ordering::Relaxed);
+        // SAFETY: We have unique ownership of the object via `&mut self`. All `Protected`s loaded
+        // from `self` are dead as they borrow `self`.
+        drop(unsafe { HpOwned::from_raw(pointer) });
+    }
+}
+
+impl<T> fmt::Debug for HpAtomic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HpAtomic")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<T> HpOwned<T> {
+    /// Allocates `value` on the heap.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Box::new(value),
+        }
+    }
+
+    /// Converts into a [`Box`].
+    pub fn into_box(self) -> Box<T> {
+        self.inner
+    }
+
+    fn into_raw(owned: Option<Self>) -> *mut T {
+        owned.map_or(ptr::null_mut(), |owned| Box::into_raw(owned.inner))
+    }
+
+    /// # Safety
+    ///
+    /// `pointer` must be null, or allocated by `Box` and uniquely owned.
+    unsafe fn from_raw(pointer: *mut T) -> Option<Self> {
+        (!pointer.is_null()).then(|| Self {
+            inner: unsafe { Box::from_raw(pointer) },
+        })
+    }
+}
+
+impl<T> From<Box<T>> for HpOwned<T> {
+    fn from(inner: Box<T>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T> Deref for HpOwned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for HpOwned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for HpOwned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HpOwned").field(&self.inner).finish()
+    }
+}
+
+impl<'s, T> Protected<'s, T> {
+    /// Returns `true` if the pointer is null.
+    pub fn is_null(&self) -> bool {
+        self.pointer.is_null()
+    }
+
+    /// Returns the raw pointer.
+    pub fn as_raw(&self) -> *mut T {
+        self.pointer
+    }
+
+    /// Returns a reference to the object, or `None` if the pointer is null.
+    pub fn as_ref(&self) -> Option<&'s T> {
+        // SAFETY: The object is protected by the shield for `'s`, and objects are retired only
+        // after they are unlinked from the `HpAtomic`.
+        unsafe { self.pointer.as_ref() }
+    }
+}
+
+impl<T> Clone for Protected<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Protected<'_, T> {}
+
+impl<T> PartialEq for Protected<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pointer == other.pointer
+    }
+}
+
+impl<T> Eq for Protected<'_, T> {}
+
+impl<T> fmt::Debug for Protected<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Protected").field(&self.pointer).finish()
+    }
+}
@@ -0,0 +1,358 @@
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread_local;
+
+use super::{HazardBag, RetiredSet, Shield};
+
+/// A hazard pointer domain.
+///
+/// A domain consists of its own bag of hazard pointers, and the retired pointer lists of the
+/// threads that retire pointers in it. Pointers retired in a domain are only checked against the
+/// hazards of the domain. So independent data structures can use separate domains so that they
+/// don't scan each other's hazards.
+///
+/// When a domain is dropped, all pointers retired in it are freed. Hence the shields of a domain,
+/// created with [`Shield::new_in`] or [`HazardDomain::shield`], borrow the domain.
+///
+/// # Example
+///
+/// ```
+/// use std::ptr;
+/// use std::sync::atomic::{AtomicPtr, Ordering};
+/// use cs431_homework::hazard_pointer::HazardDomain;
+///
+/// let domain = HazardDomain::new();
+/// let shield = domain.shield();
+/// let atomic = AtomicPtr::new(Box::leak(Box::new(1usize)));
+/// let protected = shield.protect(&atomic);
+/// assert_eq!(unsafe { *protected }, 1);
+///
+/// // unlink the block and retire
+/// atomic.store(ptr::null_mut(), Ordering::Relaxed);
+/// unsafe { domain.retire(protected); }
+///
+/// // the block is freed when the domain is dropped, if not earlier
+/// drop(shield);
+/// drop(domain);
+/// ```
+#[derive(Debug)]
+pub struct HazardDomain {
+    /// Unique identifier of the domain, used to find the current thread's retired pointer list.
+    id: usize,
+    hazards: Arc<HazardBag>,
+    /// Retired pointer lists of all threads that have retired pointers in this domain.
+    locals: Mutex<Vec<Arc<Local>>>,
+    /// Threshold of the retired pointer lists.
+    threshold: usize,
+}
+
+/// Retired pointer list of a thread in a domain.
+#[derive(Debug)]
+struct Local {
+    /// Whether this list is owned by a thread. Lists of exited threads are adopted by other
+    /// threads, together with their remaining retired pointers.
+    active: AtomicBool,
+    /// Only accessed by the owning thread, or by the domain when it is dropped. This is a
+    /// `RefCell` as the deleters may reenter the domain, as for the default thread-local list.
+    ///
+    /// NOTE: This borrows `hazards`, so must be declared (and thus dropped) before it.
+    retired: RefCell<RetiredSet<'static>>,
+    hazards: Arc<HazardBag>,
+}
+
+// SAFETY: `retired` is only accessed by the owning thread, or by the domain with exclusive access.
+unsafe impl Send for Local {}
+unsafe impl Sync for Local {}
+
+impl Local {
+    fn new(hazards: Arc<HazardBag>, threshold: usize) -> Self {
+        // SAFETY: `hazards` is kept alive by `self.hazards` for as long as `retired` is.
+        let bag = unsafe { &*Arc::as_ptr(&hazards) };
+        Self {
+            active: AtomicBool::new(true),
+            retired: RefCell::new(RetiredSet::with_threshold(bag, threshold)),
+            hazards,
+        }
+    }
+}
+
+/// The retired pointer lists owned by the current thread, with the id of their domains.
+#[derive(Debug, Default)]
+struct Locals(Vec<(usize, Arc<Local>)>);
+
+impl Drop for Locals {
+    /// Releases the lists so that other threads can adopt them.
+    fn drop(&mut self) {
+        for (_, local) in &self.0 {
+            local.active.store(false, Ordering::Release);
+        }
+    }
+}
+
+thread_local! {
+    static LOCALS: RefCell<Locals> = RefCell::new(Locals::default());
+}
+
+/// Source of unique domain ids.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl HazardDomain {
+    /// Creates a new domain.
+    pub fn new() -> Self {
+        Self::with_threshold(RetiredSet::THRESHOLD)
+    }
+
+    /// Creates a new domain, in which each thread's retired pointer list is collected when
+    /// `threshold` pointers are retired. See [`RetiredSet::with_threshold`].
+    pub fn with_threshold(threshold: usize) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            hazards: Arc::new(HazardBag::new()),
+            locals: Mutex::new(Vec::new()),
+            threshold,
+        }
+    }
+
+    /// Returns the bag of hazard pointers of this domain.
+    pub fn hazards(&self) -> &HazardBag {
+        &self.hazards
+    }
+
+    /// Creates a new shield in this domain.
+    pub fn shield(&self) -> DomainShield<'_> {
+        DomainShield {
+            shield: Shield::new(&self.hazards),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Runs `f` with the current thread's retired pointer list in this domain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` reenters the domain, e.g. if a deleter run by `collect` retires a pointer in
+    /// the same domain.
+    fn with_local<R>(&self, f: impl FnOnce(&mut RetiredSet<'static>) -> R) -> R {
+        LOCALS.with(|locals| {
+            let mut locals = locals.borrow_mut();
+            let local = match locals.0.iter().find(|(id, _)| *id == self.id) {
+                Some((_, local)) => local.clone(),
+                None => {
+                    // Forget the lists of dropped domains.
+                    locals.0.retain(|(_, local)| Arc::strong_count(local) > 1);
+                    let local = self.register();
+                    locals.0.push((self.id, local.clone()));
+                    local
+                }
+            };
+            drop(locals);
+
+            // The current thread owns `local`, so the borrow fails only if `f` reenters.
+            f(&mut local.retired.borrow_mut())
+        })
+    }
+
+    /// Adopts a list released by an exited thread, or creates a new one.
+    fn register(&self) -> Arc<Local> {
+        let mut locals = self.locals.lock().unwrap();
+        if let Some(local) = locals.iter().find(|local| {
+            local
+                .active
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        }) {
+            return local.clone();
+        }
+        let local = Arc::new(Local::new(self.hazards.clone(), self.threshold));
+        locals.push(local.clone());
+        local
+    }
+
+    /// Retires a pointer in this domain.
+    ///
+    /// # Safety
+    ///
+    /// * `pointer` must be removed from shared memory before calling this function, and must be
+    ///   valid.
+    /// * The same `pointer` should only be retired once.
+    /// * It should be safe to drop `*pointer` from any thread, e.g. `T: Send`, as the lists of
+    ///   exited threads are adopted by other threads.
+    /// * `pointer` should only be protected by the `Shield`s of this domain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called by a deleter run by [`HazardDomain::collect`] of the same domain, like
+    /// [`retire`](super::retire) does for the default thread-local list.
+    pub unsafe fn retire<T>(&self, pointer: *mut T) {
+        self.with_local(|retired| unsafe { retired.retire(pointer) });
+    }
+
+    /// Retires a pointer in this domain, which is freed by `deleter` once it is no longer
+    /// protected.
+    ///
+    /// # Safety
+    ///
+    /// See [`HazardDomain::retire`] and [`RetiredSet::retire_with`].
+    pub unsafe fn retire_with<T>(&self, pointer: *mut T, deleter: unsafe fn(*mut ())) {
+        self.with_local(|retired| unsafe { retired.retire_with(pointer, deleter) });
+    }
+
+    /// Frees the pointers that are `retire`d by the current thread in this domain and not
+    /// `protect`ed by any `Shield`s of this domain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called by a deleter run by `collect` of the same domain.
+    pub fn collect(&self) {
+        self.with_local(RetiredSet::collect);
+    }
+}
+
+impl Default for HazardDomain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for HazardDomain {
+    /// Frees all pointers retired in this domain.
+    fn drop(&mut self) {
+        for local in self.locals.get_mut().unwrap().iter() {
+            // SAFETY: No thread can access `local.retired` without a reference to the domain, and
+            // all shields of this domain are dropped as they borrow the domain.
+            unsafe { local.retired.borrow_mut().free_all() };
+        }
+    }
+}
+
+/// A [`Shield`] in a [`HazardDomain`], created by [`Shield::new_in`] or [`HazardDomain::shield`].
+///
+/// This borrows the domain, as the pointers it protects are freed when the domain is dropped.
+///
+/// ```compile_fail
+/// use cs431_homework::hazard_pointer::HazardDomain;
+///
+/// let domain = HazardDomain::new();
+/// let shield = domain.shield();
+/// drop(domain);
+/// drop(shield);
+/// ```
+#[derive(Debug)]
+pub struct DomainShield<'d> {
+    shield: Shield,
+    _marker: PhantomData<&'d HazardDomain>,
+}
+
+impl Shield {
+    /// Creates a new shield in `domain`. Same as [`HazardDomain::shield`].
+    ///
+    /// This returns a [`DomainShield`] instead of a `Shield`, which borrows `domain` so that the
+    /// domain can't be dropped while the shield protects a pointer retired in it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cs431_homework::hazard_pointer::{HazardDomain, Shield};
+    ///
+    /// let domain = HazardDomain::new();
+    /// let shield = Shield::new_in(&domain);
+    /// drop(shield);
+    /// drop(domain);
+    /// ```
+    pub fn new_in(domain: &HazardDomain) -> DomainShield<'_> {
+        domain.shield()
+    }
+}
+
+impl Deref for DomainShield<'_> {
+    type Target = Shield;
+
+    fn deref(&self) -> &Shield {
+        &self.shield
+    }
+}
+
+#[cfg(all(test, not(feature = "check-loom")))]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicPtr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::HazardDomain;
+
+    struct Tester(Arc<Mutex<HashSet<usize>>>, usize);
+    impl Drop for Tester {
+        fn drop(&mut self) {
+            let _ = self.0.lock().unwrap().insert(self.1);
+        }
+    }
+
+    // Hazards in one domain should not prevent reclamation in another.
+    #[test]
+    fn independent_domains() {
+        let freed = Arc::new(Mutex::new(HashSet::new()));
+        let domain1 = HazardDomain::new();
+        let domain2 = HazardDomain::new();
+
+        let ptr = Box::into_raw(Box::new(Tester(freed.clone(), 0)));
+        let shield = domain1.shield();
+        let _ = shield.protect(&AtomicPtr::new(ptr));
+
+        unsafe { domain2.retire(ptr) };
+        domain2.collect();
+        assert!(freed.lock().unwrap().contains(&0));
+
+        let ptr = Box::into_raw(Box::new(Tester(freed.clone(), 1)));
+        let _ = shield.protect(&AtomicPtr::new(ptr));
+        unsafe { domain1.retire(ptr) };
+        domain1.collect();
+        assert!(!freed.lock().unwrap().contains(&1));
+
+        drop(shield);
+        domain1.collect();
+        assert!(freed.lock().unwrap().contains(&1));
+    }
+
+    // Dropping a domain should free the pointers retired by all threads, including exited ones.
+    #[test]
+    fn drop_frees_retired() {
+        const THREADS: usize = 8;
+
+        let freed = Arc::new(Mutex::new(HashSet::new()));
+        let domain = HazardDomain::new();
+        thread::scope(|s| {
+            for i in 0..THREADS {
+                let domain = &domain;
+                let freed = freed.clone();
+                let _ = s.spawn(move || {
+                    let ptr = Box::into_raw(Box::new(Tester(freed, i)));
+                    unsafe { domain.retire(ptr) };
+                });
+            }
+        });
+        drop(domain);
+
+        assert_eq!(*freed.lock().unwrap(), (0..THREADS).collect());
+    }
+
+    // A deleter that retires in the same domain should be caught rather than aliasing the list.
+    #[test]
+    #[should_panic(expected = "already borrowed")]
+    fn reentrant_retire() {
+        struct Reenter(&'static HazardDomain);
+        impl Drop for Reenter {
+            fn drop(&mut self) {
+                unsafe { self.0.retire(Box::into_raw(Box::new(0usize))) };
+            }
+        }
+
+        // Leaked, as the domain is left inconsistent by the panic.
+        let domain = Box::leak(Box::new(HazardDomain::new()));
+        unsafe { domain.retire(Box::into_raw(Box::new(Reenter(domain)))) };
+        domain.collect();
+    }
+}
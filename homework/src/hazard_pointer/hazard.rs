@@ -8,17 +8,29 @@ use std::fmt;
 use loom::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering, fence};
 
 use super::HAZARDS;
+use super::metrics::{Metrics, Stats};
+use super::retire::Orphans;
 
 /// Represents the ownership of a hazard pointer slot.
 pub struct Shield {
     slot: NonNull<HazardSlot>,
+    /// The bag that `slot` belongs to.
+    hazards: NonNull<HazardBag>,
 }
 
 impl Shield {
     /// Creates a new shield for hazard pointer.
     pub fn new(hazards: &HazardBag) -> Self {
         let slot = hazards.acquire_slot().into();
-        Self { slot }
+        Self {
+            slot,
+            hazards: hazards.into(),
+        }
+    }
+
+    /// Returns `true` if this shield belongs to `hazards`.
+    pub fn is_in(&self, hazards: &HazardBag) -> bool {
+        ptr::eq(self.hazards.as_ptr(), hazards)
     }
 
     /// Store `pointer` to the hazard slot.
@@ -90,6 +102,9 @@ impl fmt::Debug for Shield {
 #[derive(Debug)]
 pub struct HazardBag {
     head: AtomicPtr<HazardSlot>,
+    /// Retired pointers left by exited threads, to be reclaimed by the other threads.
+    pub(super) orphans: Orphans,
+    pub(super) metrics: Metrics,
 }
 
 /// See `HazardBag`
@@ -115,6 +130,8 @@ impl HazardBag {
     pub const fn new() -> Self {
         Self {
             head: AtomicPtr::new(ptr::null_mut()),
+            orphans: Orphans::new(),
+            metrics: Metrics::new(),
         }
     }
 
@@ -123,6 +140,8 @@ impl HazardBag {
     pub fn new() -> Self {
         Self {
             head: AtomicPtr::new(ptr::null_mut()),
+            orphans: Orphans::new(),
+            metrics: Metrics::new(),
         }
     }
 
@@ -141,6 +160,40 @@ impl HazardBag {
     pub fn all_hazards(&self) -> HashSet<*mut ()> {
         todo!()
     }
+
+    /// Returns all the hazards in the set, sorted and deduplicated.
+    ///
+    /// Checking many pointers against the hazards, one can take this snapshot once and
+    /// `binary_search` it for each pointer. This avoids hashing, and the snapshot is more compact
+    /// than the `HashSet` returned by `all_hazards`.
+    pub fn sorted_hazards(&self) -> Vec<*mut ()> {
+        let mut hazards = self.all_hazards().into_iter().collect::<Vec<_>>();
+        hazards.sort_unstable();
+        hazards
+    }
+
+    /// Returns the reclamation metrics of the bag.
+    pub fn stats(&self) -> Stats {
+        let mut stats = self.metrics.stats();
+        let mut curr = self.head.load(Ordering::Acquire).cast_const();
+        // SAFETY: Slots are never freed until the bag is dropped.
+        while let Some(slot) = unsafe { curr.as_ref() } {
+            stats.slots_allocated += 1;
+            if slot.active.load(Ordering::Relaxed) {
+                stats.slots_active += 1;
+            }
+            curr = slot.next;
+        }
+        stats
+    }
+
+    /// Returns the pointers retired to the bag but not freed yet.
+    ///
+    /// When the process exits, these are reported for the default global [`HAZARDS`].
+    #[cfg(feature = "check-leaks")]
+    pub fn leaked(&self) -> Vec<*mut ()> {
+        self.metrics.leaked()
+    }
 }
 
 impl Default for HazardBag {
@@ -0,0 +1,134 @@
+// NOTE: The metrics use `core` atomics even when checking with loom, as they are not part of the
+// algorithm and would only blow up the state space.
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+#[cfg(feature = "check-leaks")]
+use std::collections::BTreeSet;
+#[cfg(feature = "check-leaks")]
+use std::sync::{Mutex, Once};
+
+/// A snapshot of the reclamation metrics of a [`HazardBag`](super::HazardBag).
+///
+/// The metrics are updated concurrently, so the fields may not be consistent with each other.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of hazard slots allocated in the bag.
+    pub slots_allocated: usize,
+    /// The number of hazard slots owned by `Shield`s.
+    pub slots_active: usize,
+    /// The number of pointers retired but not freed yet.
+    pub retired_pending: usize,
+    /// The number of retired pointers freed so far.
+    pub freed_total: usize,
+    /// The number of calls to `collect`.
+    pub collects: usize,
+    /// The total time spent in `collect`.
+    pub collect_duration: Duration,
+}
+
+/// Reclamation metrics of a [`HazardBag`](super::HazardBag), updated by the retired pointer lists.
+#[derive(Debug)]
+pub(crate) struct Metrics {
+    retired: AtomicUsize,
+    freed: AtomicUsize,
+    collects: AtomicUsize,
+    collect_nanos: AtomicU64,
+    /// Addresses of the pointers retired but not freed yet.
+    #[cfg(feature = "check-leaks")]
+    pending: Mutex<BTreeSet<usize>>,
+}
+
+impl Metrics {
+    pub(crate) const fn new() -> Self {
+        Self {
+            retired: AtomicUsize::new(0),
+            freed: AtomicUsize::new(0),
+            collects: AtomicUsize::new(0),
+            collect_nanos: AtomicU64::new(0),
+            #[cfg(feature = "check-leaks")]
+            pending: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Records that `pointer` is retired.
+    pub(crate) fn retired(&self, pointer: *mut ()) {
+        let _ = self.retired.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "check-leaks")]
+        {
+            #[cfg(not(feature = "check-loom"))]
+            report_at_exit();
+            let _ = self.pending.lock().unwrap().insert(pointer as usize);
+        }
+    }
+
+    /// Records that `count` pointers are freed.
+    pub(crate) fn freed(&self, count: usize) {
+        let _ = self.freed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Records that `pointers` are freed, so that they are not reported as leaked.
+    #[cfg(feature = "check-leaks")]
+    pub(crate) fn freed_pointers(&self, pointers: impl IntoIterator<Item = *mut ()>) {
+        let mut pending = self.pending.lock().unwrap();
+        for pointer in pointers {
+            let _ = pending.remove(&(pointer as usize));
+        }
+    }
+
+    /// Records a call to `collect` that took `duration`.
+    pub(crate) fn collected(&self, duration: Duration) {
+        let _ = self.collects.fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let _ = self.collect_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Returns the metrics of the retired pointers. The slot metrics are left as zero.
+    pub(crate) fn stats(&self) -> Stats {
+        let freed_total = self.freed.load(Ordering::Relaxed);
+        Stats {
+            retired_pending: self
+                .retired
+                .load(Ordering::Relaxed)
+                .saturating_sub(freed_total),
+            freed_total,
+            collects: self.collects.load(Ordering::Relaxed),
+            collect_duration: Duration::from_nanos(self.collect_nanos.load(Ordering::Relaxed)),
+            ..Stats::default()
+        }
+    }
+
+    /// Returns the pointers retired but not freed yet.
+    #[cfg(feature = "check-leaks")]
+    pub(crate) fn leaked(&self) -> Vec<*mut ()> {
+        let pending = self.pending.lock().unwrap();
+        pending.iter().map(|&addr| addr as *mut ()).collect()
+    }
+}
+
+/// Registers a handler that reports the pointers retired to the default global
+/// [`HAZARDS`](super::HAZARDS) but never freed, when the process exits.
+///
+/// The handler runs after the thread-local destructors of the main thread, so the pointers that
+/// its retired pointer list could free are not reported.
+#[cfg(all(feature = "check-leaks", not(feature = "check-loom")))]
+fn report_at_exit() {
+    unsafe extern "C" {
+        fn atexit(callback: extern "C" fn()) -> core::ffi::c_int;
+    }
+
+    extern "C" fn report() {
+        let leaked = super::HAZARDS.metrics.leaked();
+        if !leaked.is_empty() {
+            eprintln!(
+                "hazard_pointer: {} retired pointers are never freed: {:?}",
+                leaked.len(),
+                leaked
+            );
+        }
+    }
+
+    static REGISTER: Once = Once::new();
+    // SAFETY: `report` doesn't unwind, and only accesses statics.
+    REGISTER.call_once(|| assert_eq!(unsafe { atexit(report) }, 0));
+}
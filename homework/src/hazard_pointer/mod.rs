@@ -22,15 +22,25 @@
 
 use core::cell::RefCell;
 #[cfg(not(feature = "check-loom"))]
+use core::sync::atomic::AtomicPtr;
+#[cfg(not(feature = "check-loom"))]
 use std::thread_local;
 
+#[cfg(not(feature = "check-loom"))]
+use cs431::lockfree::smr::Smr;
 #[cfg(feature = "check-loom")]
 use loom::thread_local;
 
+mod atomic;
+mod domain;
 mod hazard;
+mod metrics;
 mod retire;
 
+pub use atomic::{HpAtomic, HpOwned, Protected};
+pub use domain::{DomainShield, HazardDomain};
 pub use hazard::{HazardBag, Shield};
+pub use metrics::Stats;
 pub use retire::RetiredSet;
 
 #[cfg(not(feature = "check-loom"))]
@@ -56,12 +66,68 @@ thread_local! {
 ///
 /// * `pointer` must be removed from shared memory before calling this function, and must be valid.
 /// * The same `pointer` should only be retired once.
+/// * It should be safe to drop `*pointer` from any thread, e.g. `T: Send`.
 pub unsafe fn retire<T>(pointer: *mut T) {
     RETIRED.with(|r| unsafe { r.borrow_mut().retire(pointer) });
 }
 
+/// Retires a pointer, which is freed by `deleter` once it is no longer protected.
+///
+/// # Safety
+///
+/// See [`RetiredSet::retire_with`].
+pub unsafe fn retire_with<T>(pointer: *mut T, deleter: unsafe fn(*mut ())) {
+    RETIRED.with(|r| unsafe { r.borrow_mut().retire_with(pointer, deleter) });
+}
+
 /// Frees the pointers that are `retire`d by the current thread and not `protect`ed by any other
 /// threads.
 pub fn collect() {
     RETIRED.with(|r| r.borrow_mut().collect());
 }
+
+/// Hazard pointer based reclamation for the data structures in [`cs431::lockfree`], using the
+/// default global [`HAZARDS`] and the default thread-local retired pointer list.
+///
+/// # Example
+///
+/// ```
+/// use cs431::lockfree::Stack;
+/// use cs431_homework::hazard_pointer::HazardPointer;
+///
+/// let stack = Stack::<_, HazardPointer>::default();
+/// stack.push(1);
+/// assert_eq!(stack.pop(), Some(1));
+/// ```
+#[cfg(not(feature = "check-loom"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HazardPointer;
+
+#[cfg(not(feature = "check-loom"))]
+unsafe impl Smr for HazardPointer {
+    type Guard = ();
+
+    type Shield = Shield;
+
+    fn pin() {}
+
+    fn shield(_guard: &()) -> Shield {
+        Shield::default()
+    }
+
+    fn set<T>(shield: &Shield, pointer: *mut T) {
+        shield.set(pointer)
+    }
+
+    fn validate<T>(pointer: *mut T, src: &AtomicPtr<T>) -> Result<(), *mut T> {
+        Shield::validate(pointer, src)
+    }
+
+    fn protect<T>(shield: &Shield, src: &AtomicPtr<T>) -> *mut T {
+        shield.protect(src)
+    }
+
+    unsafe fn retire<T>(_guard: &(), pointer: *mut T) {
+        unsafe { retire(pointer) }
+    }
+}
@@ -1,6 +1,11 @@
 use core::marker::PhantomData;
+use core::sync::atomic::AtomicPtr;
 #[cfg(not(feature = "check-loom"))]
 use core::sync::atomic::{Ordering, fence};
+use core::{mem, ptr};
+#[cfg(feature = "check-leaks")]
+use std::collections::HashSet;
+use std::time::Instant;
 
 #[cfg(feature = "check-loom")]
 use loom::sync::atomic::{Ordering, fence};
@@ -9,63 +14,223 @@ use super::{HAZARDS, HazardBag};
 
 type Retired = (*mut (), unsafe fn(*mut ()));
 
+/// Lock-free list of retired pointers orphaned by exited threads.
+///
+/// When a thread exits with some of its retired pointers still protected, it pushes them here as a
+/// batch instead of waiting for them to be unprotected. Other threads adopt all batches at once in
+/// `collect`.
+#[derive(Debug)]
+pub(crate) struct Orphans {
+    head: AtomicPtr<OrphanBatch>,
+}
+
+#[derive(Debug)]
+struct OrphanBatch {
+    retired: Vec<Retired>,
+    next: *mut OrphanBatch,
+}
+
+impl Orphans {
+    pub(crate) const fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Pushes a batch of retired pointers.
+    fn push(&self, retired: Vec<Retired>) {
+        let batch = Box::leak(Box::new(OrphanBatch {
+            retired,
+            next: ptr::null_mut(),
+        }));
+
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            batch.next = head;
+            match self
+                .head
+                .compare_exchange(head, batch, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Takes all orphaned retired pointers.
+    fn take(&self) -> Vec<Retired> {
+        // Fast path to avoid contention when there are no orphans.
+        if self.head.load(Ordering::Relaxed).is_null() {
+            return Vec::new();
+        }
+
+        // Taking the whole list at once, we uniquely own the batches, so there is no ABA or
+        // reclamation problem.
+        let mut curr = self.head.swap(ptr::null_mut(), Ordering::Acquire);
+        let mut retired = Vec::new();
+        while !curr.is_null() {
+            // SAFETY: The batches are allocated by `push` and uniquely owned by us.
+            let batch = unsafe { Box::from_raw(curr) };
+            retired.extend(batch.retired);
+            curr = batch.next;
+        }
+        retired
+    }
+}
+
+impl Drop for Orphans {
+    /// Frees all orphaned pointers. This is safe as the `HazardBag` is being dropped, so there are
+    /// no `Shield`s that protect them.
+    fn drop(&mut self) {
+        for (data, free) in self.take() {
+            unsafe { free(data) };
+        }
+    }
+}
+
+/// Frees a pointer allocated by [`Box`]. This is the deleter used by [`RetiredSet::retire`].
+///
+/// # Safety
+///
+/// * Subsumes the safety requirements of [`Box::from_raw`]. In particular, one must have unique
+///   ownership to `data`.
+///
+/// [`Box::from_raw`]: https://doc.rust-lang.org/std/boxed/struct.Box.html#method.from_raw
+unsafe fn free<T>(data: *mut ()) {
+    drop(unsafe { Box::from_raw(data.cast::<T>()) })
+}
+
 /// Thread-local list of retired pointers.
 #[derive(Debug)]
 pub struct RetiredSet<'s> {
     hazards: &'s HazardBag,
     /// The first element of the pair is the machine representation of the pointer and the second
-    /// is the function pointer to `free::<T>` where `T` is the type of the object.
+    /// is the deleter of the object, e.g. `free::<T>` where `T` is the type of the object.
     inner: Vec<Retired>,
+    /// `collect` is triggered when `threshold` pointers are retired.
+    threshold: usize,
     _marker: PhantomData<*const ()>, // !Send + !Sync
 }
 
 impl<'s> RetiredSet<'s> {
-    /// The max length of retired pointer list. `collect` is triggered when `THRESHOLD` pointers
-    /// are retired.
-    const THRESHOLD: usize = 64;
+    /// The default max length of retired pointer list. `collect` is triggered when `THRESHOLD`
+    /// pointers are retired.
+    pub const THRESHOLD: usize = 64;
 
     /// Create a new retired pointer list protected by the given `HazardBag`.
     pub fn new(hazards: &'s HazardBag) -> Self {
+        Self::with_threshold(hazards, Self::THRESHOLD)
+    }
+
+    /// Create a new retired pointer list protected by the given `HazardBag`, for which `collect` is
+    /// triggered when `threshold` pointers are retired.
+    ///
+    /// A larger threshold amortizes the cost of scanning the hazards over more retired pointers, at
+    /// the cost of more memory waiting to be reclaimed. For the scan to free a constant fraction of
+    /// the retired pointers, the threshold should be proportional to the number of hazards.
+    pub fn with_threshold(hazards: &'s HazardBag, threshold: usize) -> Self {
         Self {
             hazards,
             inner: Vec::new(),
+            threshold,
             _marker: PhantomData,
         }
     }
 
-    /// Retires a pointer.
+    /// Returns the threshold of this list. See [`RetiredSet::with_threshold`].
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Retires a pointer allocated by [`Box`].
     ///
     /// # Safety
     ///
     /// * `pointer` must be removed from shared memory before calling this function, and must be
     ///   valid.
     /// * The same `pointer` should only be retired once.
+    /// * It should be safe to drop `*pointer` from any thread, e.g. `T: Send`.
     ///
     /// # Note
     ///
-    /// `T: Send` is not required because the retired pointers are not sent to other threads.
+    /// The pointers still protected when a thread exits are freed by other threads, which is why
+    /// `T` should be safe to send, though it is not required by a bound.
     pub unsafe fn retire<T>(&mut self, pointer: *mut T) {
-        /// Frees a pointer. This function is defined here instead of `collect()` as we know about
-        /// the type of `pointer` only at the time of retiring it.
-        ///
-        /// # Safety
-        ///
-        /// * Subsumes the safety requirements of [`Box::from_raw`]. In particular, one must have
-        ///   unique ownership to `data`.
-        ///
-        /// [`Box::from_raw`]: https://doc.rust-lang.org/std/boxed/struct.Box.html#method.from_raw
-        unsafe fn free<T>(data: *mut ()) {
-            drop(unsafe { Box::from_raw(data.cast::<T>()) })
-        }
+        unsafe { self.retire_with(pointer, free::<T>) }
+    }
+
+    /// Retires a pointer, which is freed by `deleter` once it is no longer protected.
+    ///
+    /// This is useful for objects that are not allocated by [`Box`], e.g. objects allocated by
+    /// another allocator or embedded in a slab. `deleter` is called with `pointer` cast to `*mut
+    /// ()`.
+    ///
+    /// # Safety
+    ///
+    /// * `pointer` must be removed from shared memory before calling this function, and must be
+    ///   valid.
+    /// * The same `pointer` should only be retired once.
+    /// * It should be safe to call `deleter` with `pointer` once it is no longer protected, from
+    ///   any thread that calls `collect`.
+    pub unsafe fn retire_with<T>(&mut self, pointer: *mut T, deleter: unsafe fn(*mut ())) {
+        self.hazards.metrics.retired(pointer.cast());
 
         todo!()
     }
 
     /// Free the pointers that are `retire`d by the current thread and not `protect`ed by any other
     /// threads.
+    ///
+    /// Also adopts the pointers orphaned by exited threads, so that they are freed here if not
+    /// protected.
     pub fn collect(&mut self) {
+        let start = Instant::now();
+        self.inner.extend(self.hazards.orphans.take());
+        let before_len = self.inner.len();
+        #[cfg(feature = "check-leaks")]
+        let retired = self.inner.iter().map(|&(data, _)| data).collect::<Vec<_>>();
+
+        self.reclaim();
+
+        self.hazards.metrics.freed(before_len - self.inner.len());
+        #[cfg(feature = "check-leaks")]
+        {
+            let remaining = self
+                .inner
+                .iter()
+                .map(|&(data, _)| data)
+                .collect::<HashSet<_>>();
+            self.hazards
+                .metrics
+                .freed_pointers(retired.into_iter().filter(|data| !remaining.contains(data)));
+        }
+        self.hazards.metrics.collected(start.elapsed());
+    }
+
+    /// Frees the pointers in `self.inner` that are not `protect`ed by any threads.
+    ///
+    /// The hazards are scanned once per call, e.g. with [`HazardBag::sorted_hazards`], rather than
+    /// once per retired pointer.
+    fn reclaim(&mut self) {
         todo!()
     }
+
+    /// Frees all retired pointers, regardless of whether they are protected.
+    ///
+    /// # Safety
+    ///
+    /// None of the retired pointers may be protected or accessed by any thread.
+    pub(crate) unsafe fn free_all(&mut self) {
+        for &(data, free) in &self.inner {
+            unsafe { free(data) };
+        }
+        self.hazards.metrics.freed(self.inner.len());
+        #[cfg(feature = "check-leaks")]
+        self.hazards
+            .metrics
+            .freed_pointers(self.inner.iter().map(|&(data, _)| data));
+        self.inner.clear();
+    }
 }
 
 impl Default for RetiredSet<'static> {
@@ -78,12 +243,13 @@ impl Default for RetiredSet<'static> {
 #[cfg(not(feature = "check-loom"))]
 impl Drop for RetiredSet<'_> {
     fn drop(&mut self) {
-        // In a production-quality implementation of hazard pointers, the remaining local retired
-        // pointers will be moved to a global list of retired pointers, which are then reclaimed by
-        // the other threads. For pedagogical purposes, here we simply wait for all retired pointers
-        // are no longer protected.
-        while !self.inner.is_empty() {
-            self.collect();
+        // Try to free the remaining local retired pointers. Those that are still protected are
+        // moved to the global list of orphaned retired pointers, which are then reclaimed by the
+        // other threads, so that the exiting thread doesn't wait for the other threads to
+        // unprotect them.
+        self.collect();
+        if !self.inner.is_empty() {
+            self.hazards.orphans.push(mem::take(&mut self.inner));
         }
     }
 }
@@ -93,8 +259,13 @@ mod tests {
     use std::cell::RefCell;
     use std::collections::HashSet;
     use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering::Relaxed;
+    use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize};
+    use std::thread;
 
     use super::{HazardBag, RetiredSet};
+    use crate::hazard_pointer::Shield;
 
     // retire `THRESHOLD` pointers to trigger collection
     #[test]
@@ -115,4 +286,95 @@ mod tests {
 
         assert_eq!(freed, (0..RetiredSet::THRESHOLD).collect())
     }
+
+    // retire pointers with a custom deleter and a custom threshold to trigger collection
+    #[test]
+    fn retire_with_deleter() {
+        const THRESHOLD: usize = 8;
+        static FREED: AtomicUsize = AtomicUsize::new(0);
+
+        // Objects are embedded in a slab, which outlives the retired set.
+        let slab = (0..THRESHOLD).map(AtomicUsize::new).collect::<Vec<_>>();
+        unsafe fn deleter(data: *mut ()) {
+            let slot = unsafe { &*data.cast::<AtomicUsize>() };
+            let _ = FREED.fetch_add(slot.swap(usize::MAX, Relaxed), Relaxed);
+        }
+
+        let hazards = HazardBag::new();
+        let mut retires = RetiredSet::with_threshold(&hazards, THRESHOLD);
+        assert_eq!(retires.threshold(), THRESHOLD);
+        for slot in &slab {
+            unsafe { retires.retire_with(slot as *const _ as *mut AtomicUsize, deleter) };
+        }
+
+        assert_eq!(FREED.load(Relaxed), (0..THRESHOLD).sum());
+        assert!(slab.iter().all(|slot| slot.load(Relaxed) == usize::MAX));
+    }
+
+    // `stats` should count the hazard slots and the retired pointers.
+    #[test]
+    fn stats() {
+        let hazards = HazardBag::new();
+        let mut shields = (0..4).map(|_| Shield::new(&hazards)).collect::<Vec<_>>();
+        drop(shields.pop());
+        let pointer = Box::into_raw(Box::new(0usize));
+        let _ = shields[0].protect(&AtomicPtr::new(pointer));
+
+        let mut retires = RetiredSet::new(&hazards);
+        unsafe { retires.retire(pointer) };
+        for i in 0..3 {
+            unsafe { retires.retire(Box::into_raw(Box::new(i))) };
+        }
+        retires.collect();
+
+        let stats = hazards.stats();
+        assert_eq!(stats.slots_allocated, 4);
+        assert_eq!(stats.slots_active, 3);
+        assert_eq!(stats.retired_pending, 1);
+        assert_eq!(stats.freed_total, 3);
+        assert_eq!(stats.collects, 1);
+        #[cfg(feature = "check-leaks")]
+        assert_eq!(hazards.leaked(), vec![pointer.cast()]);
+
+        drop(shields);
+        retires.collect();
+        let stats = hazards.stats();
+        assert_eq!(stats.slots_active, 0);
+        assert_eq!(stats.retired_pending, 0);
+        assert_eq!(stats.freed_total, 4);
+        #[cfg(feature = "check-leaks")]
+        assert!(hazards.leaked().is_empty());
+    }
+
+    // retired pointers that are protected when the thread exits should be adopted and freed by
+    // another thread.
+    #[test]
+    fn orphan_adopted() {
+        struct Tester(Arc<AtomicBool>);
+        impl Drop for Tester {
+            fn drop(&mut self) {
+                self.0.store(true, Relaxed);
+            }
+        }
+        let hazards = HazardBag::new();
+        let freed = Arc::new(AtomicBool::new(false));
+        let pointer = Box::into_raw(Box::new(Tester(freed.clone())));
+        let shield = Shield::new(&hazards);
+        let _ = shield.protect(&AtomicPtr::new(pointer));
+
+        // The thread exits without waiting for `pointer` to be unprotected.
+        let addr = pointer as usize;
+        thread::scope(|s| {
+            let _ = s.spawn(|| {
+                let mut retires = RetiredSet::new(&hazards);
+                unsafe { retires.retire(addr as *mut Tester) };
+            });
+        });
+        assert!(!freed.load(Relaxed));
+
+        drop(shield);
+        let mut retires = RetiredSet::new(&hazards);
+        retires.collect();
+        assert!(freed.load(Relaxed));
+    }
 }
@@ -8,9 +8,13 @@
 
 mod adt;
 mod arc;
+mod biased_arc;
 pub mod boc;
+mod concurrent_vec;
 mod elim_stack;
 mod hash_table;
+#[cfg(not(feature = "check-loom"))]
+pub mod hazard_era;
 pub mod hazard_pointer;
 pub mod hello_server;
 mod linked_list;
@@ -19,9 +23,11 @@ mod list_set;
 pub mod test;
 
 pub use adt::{ConcurrentMap, ConcurrentSet};
-pub use arc::Arc;
-pub use boc::CownPtr;
+pub use arc::{Arc, AtomicArc, Weak};
+pub use biased_arc::BiasedArc;
+pub use boc::{BocRuntime, CownPtr, CownReadPtr, Promise};
+pub use concurrent_vec::ConcurrentVec;
 pub use elim_stack::ElimStack;
-pub use hash_table::{GrowableArray, SplitOrderedList};
+pub use hash_table::{GrowableArray, OpenAddressingMap, SplitOrderedList};
 pub use linked_list::LinkedList;
 pub use list_set::{FineGrainedListSet, OptimisticFineGrainedListSet};
@@ -182,8 +182,18 @@ enum Ops {
     Lookup,
     Insert,
     Delete,
+    GetOrInsert,
+    Upsert,
+    CompareAndReplace,
 }
-const OPS: [Ops; 3] = [Ops::Lookup, Ops::Insert, Ops::Delete];
+const OPS: [Ops; 6] = [
+    Ops::Lookup,
+    Ops::Insert,
+    Ops::Delete,
+    Ops::GetOrInsert,
+    Ops::Upsert,
+    Ops::CompareAndReplace,
+];
 
 #[derive(Clone)]
 /// Successful operations are logged as `Some`. Failed operations are `None`.
@@ -205,7 +215,7 @@ impl<K, V> Log<K, V> {
 
 /// Randomly runs many operations concurrently.
 pub fn stress_concurrent<
-    K: Debug + Eq + RandGen,
+    K: Clone + Debug + Eq + RandGen,
     V: Debug + Eq + RandGen,
     M: Default + Sync + ConcurrentMap<K, V>,
 >(
@@ -233,6 +243,19 @@ pub fn stress_concurrent<
                         Ops::Delete => {
                             let _ = map.delete(&key, &pin());
                         }
+                        Ops::GetOrInsert => {
+                            let _ = map.get_or_insert_with(key, || V::rand_gen(&mut rng), &pin());
+                        }
+                        Ops::Upsert => {
+                            let _ = map.upsert(key, |_| V::rand_gen(&mut rng), &pin());
+                        }
+                        Ops::CompareAndReplace => {
+                            let guard = pin();
+                            if let Some(expected) = map.lookup(&key, &guard) {
+                                let new = V::rand_gen(&mut rng);
+                                let _ = map.compare_and_replace(&key, expected, new, &guard);
+                            }
+                        }
                     }
                 }
             });
@@ -288,6 +311,10 @@ fn assert_logs_consistent<K: Debug + Eq + Hash, V: Debug + Eq + Hash>(logs: &[Lo
 /// Randomly runs many operations concurrently and logs the operations & results per thread. Then
 /// checks the consistency of the log. For example, if the key `k` was successfully deleted twice,
 /// then `k` must have been inserted at least twice.
+///
+/// The atomic updates are logged as the lookups, insertions and deletions they consist of, e.g. a
+/// successful `compare_and_replace` as the deletion of the old value and the insertion of the new
+/// value.
 pub fn log_concurrent<
     K: Clone + Debug + Eq + Hash + RandGen + Send,
     V: Clone + Debug + Eq + Hash + RandGen + Send,
@@ -327,6 +354,64 @@ pub fn log_concurrent<
                             let result = map.delete(&key, &pin()).cloned().ok();
                             logs.push(Log::Delete { key, result });
                         }
+                        Ops::GetOrInsert => {
+                            // Only a value created by `f` and returned is known to be inserted.
+                            let mut values = Vec::new();
+                            let f = || {
+                                let value = V::rand_gen(&mut rng);
+                                values.push(value.clone());
+                                value
+                            };
+                            let result = map.get_or_insert_with(key.clone(), f, &pin()).clone();
+                            if values.contains(&result) {
+                                logs.push(Log::Insert {
+                                    key: key.clone(),
+                                    result: Some(result.clone()),
+                                });
+                            }
+                            logs.push(Log::Lookup {
+                                key,
+                                result: Some(result),
+                            });
+                        }
+                        Ops::Upsert => {
+                            let value = V::rand_gen(&mut rng);
+                            let old = map.upsert(key.clone(), |_| value.clone(), &pin()).cloned();
+                            if old.is_some() {
+                                logs.push(Log::Delete {
+                                    key: key.clone(),
+                                    result: old,
+                                });
+                            }
+                            logs.push(Log::Insert {
+                                key,
+                                result: Some(value),
+                            });
+                        }
+                        Ops::CompareAndReplace => {
+                            let guard = pin();
+                            let expected = map.lookup(&key, &guard).cloned();
+                            logs.push(Log::Lookup {
+                                key: key.clone(),
+                                result: expected.clone(),
+                            });
+                            let Some(expected) = expected else {
+                                continue;
+                            };
+                            let new = V::rand_gen(&mut rng);
+                            if let Ok(old) =
+                                map.compare_and_replace(&key, &expected, new.clone(), &guard)
+                            {
+                                logs.push(Log::Delete {
+                                    key: key.clone(),
+                                    result: Some(old.clone()),
+                                });
+                                logs.push(Log::Insert {
+                                    key,
+                                    result: Some(new),
+                                });
+                            }
+                        }
                     }
                 }
                 logs
@@ -10,17 +10,42 @@ use crate::test::RandGen;
 use crate::{ConcurrentMap, ConcurrentSet};
 
 // A set can be seen as a map with value `()`. Thus, we can reuse the tests for maps.
-impl<T, S: ConcurrentSet<T>> ConcurrentMap<T, ()> for S {
+//
+// NOTE: This is a wrapper rather than a blanket implementation for all sets, as the latter would
+// conflict with the implementations for generic maps such as `SplitOrderedList<K, V, S>`.
+#[derive(Debug, Default)]
+struct SetMap<S>(S);
+
+impl<T, S: ConcurrentSet<T>> ConcurrentMap<T, ()> for SetMap<S> {
     fn lookup<'a>(&'a self, key: &T, _guard: &'a Guard) -> Option<&'a ()> {
-        if self.contains(key) { Some(&()) } else { None }
+        if self.0.contains(key) {
+            Some(&())
+        } else {
+            None
+        }
     }
 
     fn insert(&self, key: T, _value: (), _guard: &Guard) -> Result<(), ()> {
-        if self.insert(key) { Ok(()) } else { Err(()) }
+        if self.0.insert(key) { Ok(()) } else { Err(()) }
     }
 
     fn delete<'a>(&'a self, key: &T, _guard: &'a Guard) -> Result<&'a (), ()> {
-        if self.remove(key) { Ok(&()) } else { Err(()) }
+        if self.0.remove(key) { Ok(&()) } else { Err(()) }
+    }
+
+    // Replacing `()` with `()` is a no-op.
+    fn compare_and_replace<'a>(
+        &'a self,
+        key: &T,
+        _expected: &(),
+        new: (),
+        _guard: &'a Guard,
+    ) -> Result<&'a (), ()> {
+        if self.0.contains(key) {
+            Ok(&())
+        } else {
+            Err(new)
+        }
     }
 }
 
@@ -28,15 +53,15 @@ impl<T, S: ConcurrentSet<T>> ConcurrentMap<T, ()> for S {
 pub fn stress_sequential<T: Debug + Clone + Eq + Hash + RandGen, S: Default + ConcurrentSet<T>>(
     steps: usize,
 ) {
-    map::stress_sequential::<T, (), S>(steps);
+    map::stress_sequential::<T, (), SetMap<S>>(steps);
 }
 
 /// See `map::stress_concurrent`.
-pub fn stress_concurrent<T: Debug + Eq + RandGen, S: Default + Sync + ConcurrentSet<T>>(
+pub fn stress_concurrent<T: Clone + Debug + Eq + RandGen, S: Default + Sync + ConcurrentSet<T>>(
     threads: usize,
     steps: usize,
 ) {
-    map::stress_concurrent::<T, (), S>(threads, steps);
+    map::stress_concurrent::<T, (), SetMap<S>>(threads, steps);
 }
 
 /// See `map::log_concurrent`.
@@ -47,5 +72,5 @@ pub fn log_concurrent<
     threads: usize,
     steps: usize,
 ) {
-    map::log_concurrent::<T, (), S>(threads, steps);
+    map::log_concurrent::<T, (), SetMap<S>>(threads, steps);
 }
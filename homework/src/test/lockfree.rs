@@ -0,0 +1,149 @@
+//! Testing utilities for the data structures in `cs431::lockfree`, generic over the reclamation
+//! scheme. These are ports of the tests in `cs431`, which only run with `crossbeam_epoch`.
+
+use std::thread::scope;
+
+use cs431::lockfree::smr::Smr;
+use cs431::lockfree::{Queue, Stack};
+
+/// Each thread pushes and pops `steps` elements.
+pub fn stack_push_pop<R: Smr>(threads: usize, steps: usize) {
+    let stack = Stack::<_, R>::default();
+
+    scope(|scope| {
+        for _ in 0..threads {
+            let _ = scope.spawn(|| {
+                for i in 0..steps {
+                    stack.push(i);
+                    assert!(stack.pop().is_some());
+                }
+            });
+        }
+    });
+
+    assert!(stack.is_empty());
+}
+
+/// Each thread pushes `steps` elements, then all of them are taken at once.
+pub fn stack_take_all<R: Smr>(threads: usize, steps: usize) {
+    let stack = Stack::<_, R>::default();
+
+    scope(|scope| {
+        for t in 0..threads {
+            let stack = &stack;
+            let _ = scope.spawn(move || {
+                for i in 0..steps {
+                    stack.push(t * steps + i);
+                }
+            });
+        }
+    });
+
+    let mut taken = stack.take_all().collect::<Vec<_>>();
+    assert!(stack.is_empty());
+
+    taken.sort_unstable();
+    assert!(taken.into_iter().eq(0..threads * steps));
+}
+
+/// A single producer pushes `count` elements, and a single consumer pops them in order.
+pub fn queue_spsc<R: Smr>(count: usize) {
+    let queue = Queue::<_, R>::default();
+
+    scope(|scope| {
+        let _ = scope.spawn(|| {
+            let mut next = 0;
+            while next < count {
+                if let Some(elem) = queue.try_pop(&mut R::pin()) {
+                    assert_eq!(elem, next);
+                    next += 1;
+                }
+            }
+        });
+
+        for i in 0..count {
+            queue.push(i, &mut R::pin());
+        }
+    });
+
+    assert!(queue.try_pop(&mut R::pin()).is_none());
+}
+
+/// A single producer pushes `count` elements, and each of `consumers` observes them in order.
+pub fn queue_spmc<R: Smr>(consumers: usize, count: usize) {
+    let queue = Queue::<_, R>::default();
+
+    scope(|scope| {
+        for _ in 0..consumers {
+            let _ = scope.spawn(|| {
+                let mut last = None;
+                for _ in 0..count {
+                    if let Some(elem) = queue.try_pop(&mut R::pin()) {
+                        assert!(last < Some(elem));
+                        last = Some(elem);
+
+                        if elem == count - 1 {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+
+        let _ = scope.spawn(|| {
+            for i in 0..count {
+                queue.push(i, &mut R::pin());
+            }
+        });
+    });
+}
+
+/// Two producers push `count` elements each, and two consumers pop them. The elements from each
+/// producer are popped in order, and no element is lost.
+pub fn queue_mpmc<R: Smr>(count: usize) {
+    let queue = Queue::<_, R>::default();
+
+    let popped = scope(|scope| {
+        for p in 0..2 {
+            let queue = &queue;
+            let _ = scope.spawn(move || {
+                for i in 0..count {
+                    queue.push((p, i), &mut R::pin());
+                }
+            });
+        }
+
+        let consumers = (0..2)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut popped = [vec![], vec![]];
+                    for _ in 0..count {
+                        if let Some((p, i)) = queue.try_pop(&mut R::pin()) {
+                            popped[p].push(i);
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect::<Vec<_>>();
+
+        consumers
+            .into_iter()
+            .map(|c| c.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut remaining = [vec![], vec![]];
+    while let Some((p, i)) = queue.try_pop(&mut R::pin()) {
+        remaining[p].push(i);
+    }
+
+    for (p, mut all) in remaining.into_iter().enumerate() {
+        for consumer in &popped {
+            assert!(consumer[p].is_sorted());
+            all.extend_from_slice(&consumer[p]);
+        }
+        all.sort_unstable();
+        assert!(all.into_iter().eq(0..count));
+    }
+}
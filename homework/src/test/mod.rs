@@ -2,6 +2,7 @@
 // <https://stackoverflow.com/a/44541071>
 
 pub mod adt;
+pub mod lockfree;
 pub mod loom;
 pub mod rand;
 
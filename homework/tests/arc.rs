@@ -144,6 +144,311 @@ mod basic {
     }
 }
 
+#[cfg(not(feature = "check-loom"))]
+mod weak {
+    use std::sync::Mutex;
+    use std::thread::scope;
+
+    use cs431_homework::test::loom::sync::atomic::AtomicUsize;
+    use cs431_homework::test::loom::sync::atomic::Ordering::Relaxed;
+    use cs431_homework::{Arc, Weak};
+
+    use super::Canary;
+
+    #[test]
+    fn upgrade() {
+        let canary = AtomicUsize::new(0);
+        let x = Arc::new(Canary(&canary));
+        let w = Arc::downgrade(&x);
+        assert_eq!(Arc::weak_count(&x), 1);
+        assert_eq!(w.strong_count(), 1);
+
+        let y = w.upgrade().unwrap();
+        assert!(Arc::ptr_eq(&x, &y));
+        assert_eq!(Arc::count(&x), 2);
+
+        let w2 = w.clone();
+        assert_eq!(Arc::weak_count(&x), 2);
+        drop(w2);
+        assert_eq!(Arc::weak_count(&x), 1);
+
+        drop(x);
+        drop(y);
+        // The data is dropped even though the allocation is kept by `w`.
+        assert_eq!(canary.load(Relaxed), 1);
+        assert_eq!(w.strong_count(), 0);
+        assert!(w.upgrade().is_none());
+        drop(w);
+        assert_eq!(canary.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn get_mut_make_mut() {
+        let mut x = Arc::new(5);
+        let w = Arc::downgrade(&x);
+        assert!(Arc::get_mut(&mut x).is_none());
+
+        // `make_mut` clones the data, so that `w` can't observe the mutation.
+        *Arc::make_mut(&mut x) += 1;
+        assert_eq!(*x, 6);
+        assert!(w.upgrade().is_none());
+        assert_eq!(Arc::weak_count(&x), 0);
+        assert!(Arc::get_mut(&mut x).is_some());
+    }
+
+    #[test]
+    fn try_unwrap() {
+        let x = Arc::new(String::from("foo"));
+        let w = Arc::downgrade(&x);
+        assert_eq!(Arc::try_unwrap(x).unwrap(), "foo");
+        assert!(w.upgrade().is_none());
+    }
+
+    #[test]
+    fn cycle() {
+        struct Node {
+            parent: Mutex<Option<Weak<Node>>>,
+            children: Mutex<Vec<Arc<Node>>>,
+            _canary: Canary,
+        }
+
+        let canary = AtomicUsize::new(0);
+        let node = || {
+            Arc::new(Node {
+                parent: Mutex::new(None),
+                children: Mutex::new(Vec::new()),
+                _canary: Canary(&canary),
+            })
+        };
+
+        let parent = node();
+        for _ in 0..4 {
+            let child = node();
+            *child.parent.lock().unwrap() = Some(Arc::downgrade(&parent));
+            parent.children.lock().unwrap().push(child);
+        }
+        let child = parent.children.lock().unwrap()[0].clone();
+        let up = child.parent.lock().unwrap().as_ref().unwrap().upgrade();
+        assert!(Arc::ptr_eq(&up.unwrap(), &parent));
+
+        drop(parent);
+        assert_eq!(canary.load(Relaxed), 4);
+        assert!(
+            child
+                .parent
+                .lock()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .upgrade()
+                .is_none()
+        );
+        drop(child);
+        assert_eq!(canary.load(Relaxed), 5);
+    }
+
+    #[test]
+    fn upgrade_concurrent() {
+        const THREADS: usize = 4;
+        const STEPS: usize = 10_000;
+
+        for _ in 0..100 {
+            let canary = AtomicUsize::new(0);
+            let x = Arc::new(Canary(&canary));
+            let w = Arc::downgrade(&x);
+            let canary = &canary;
+            scope(|s| {
+                for _ in 0..THREADS {
+                    let w = w.clone();
+                    s.spawn(move || {
+                        for _ in 0..STEPS {
+                            let Some(y) = w.upgrade() else {
+                                break;
+                            };
+                            // The data is alive while `y` is alive.
+                            assert_eq!(canary.load(Relaxed), 0);
+                            drop(y);
+                        }
+                    });
+                }
+                drop(x);
+            });
+            assert_eq!(canary.load(Relaxed), 1);
+            assert!(w.upgrade().is_none());
+        }
+    }
+}
+
+#[cfg(not(feature = "check-loom"))]
+mod unsize {
+    use std::fmt::Display;
+    use std::thread::scope;
+
+    use cs431_homework::Arc;
+    use cs431_homework::test::loom::sync::atomic::AtomicUsize;
+    use cs431_homework::test::loom::sync::atomic::Ordering::Relaxed;
+
+    use super::Canary;
+
+    #[test]
+    fn str() {
+        let x: Arc<str> = Arc::from("foo");
+        let y = x.clone();
+        assert_eq!(&*y, "foo");
+        assert_eq!(Arc::count(&x), 2);
+
+        let w = Arc::downgrade(&x);
+        drop(x);
+        drop(y);
+        assert!(w.upgrade().is_none());
+
+        let x: Arc<str> = Arc::from(String::from(""));
+        assert_eq!(&*x, "");
+    }
+
+    #[test]
+    fn slice_drop_once() {
+        let canary = AtomicUsize::new(0);
+        let v = (0..8).map(|_| Canary(&canary)).collect::<Vec<_>>();
+        let x: Arc<[Canary]> = Arc::from(v);
+        assert_eq!(x.len(), 8);
+        assert_eq!(canary.load(Relaxed), 0);
+
+        scope(|s| {
+            for _ in 0..4 {
+                let x = x.clone();
+                s.spawn(move || assert_eq!(x.len(), 8));
+            }
+        });
+        drop(x);
+        assert_eq!(canary.load(Relaxed), 8);
+
+        let x: Arc<[u64]> = Arc::from(&[1, 2, 3][..]);
+        assert_eq!(&*x, [1, 2, 3]);
+        let x: Arc<[()]> = Arc::from(vec![(); 3]);
+        assert_eq!(x.len(), 3);
+    }
+
+    #[test]
+    fn dyn_trait() {
+        trait Named {
+            fn name(&self) -> String;
+        }
+
+        struct Dog {
+            age: u64,
+            _canary: Canary,
+        }
+        impl Named for Dog {
+            fn name(&self) -> String {
+                format!("dog {}", self.age)
+            }
+        }
+
+        let canary = AtomicUsize::new(0);
+        let x: Arc<dyn Named + Send + Sync> = Arc::from(Box::new(Dog {
+            age: 7,
+            _canary: Canary(&canary),
+        }) as Box<_>);
+        let y = x.clone();
+        assert_eq!(y.name(), "dog 7");
+        drop(x);
+        assert_eq!(canary.load(Relaxed), 0);
+        drop(y);
+        assert_eq!(canary.load(Relaxed), 1);
+
+        // Over-aligned data.
+        #[repr(align(64))]
+        struct Aligned(u8);
+        impl Display for Aligned {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+        let x: Arc<dyn Display> = Arc::from_box(Box::new(Aligned(42)));
+        assert_eq!((&*x as *const dyn Display).cast::<u8>() as usize % 64, 0);
+        assert_eq!(x.to_string(), "42");
+    }
+
+    #[test]
+    fn new_uninit() {
+        let mut x = Arc::<String>::new_uninit();
+        let _ = Arc::get_mut(&mut x).unwrap().write(String::from("foo"));
+        let x = unsafe { x.assume_init() };
+        let y = x.clone();
+        assert_eq!(*y, "foo");
+        assert!(Arc::ptr_eq(&Arc::try_unwrap(x).unwrap_err(), &y));
+    }
+}
+
+#[cfg(not(feature = "check-loom"))]
+mod atomic {
+    use std::thread::{scope, yield_now};
+
+    use cs431_homework::test::loom::sync::atomic::AtomicUsize;
+    use cs431_homework::test::loom::sync::atomic::Ordering::Relaxed;
+    use cs431_homework::{Arc, AtomicArc};
+
+    use super::Canary;
+
+    #[test]
+    fn load_store_swap() {
+        let one = Arc::new(1);
+        let atomic = AtomicArc::new(one.clone());
+        assert!(Arc::ptr_eq(&atomic.load(), &one));
+
+        atomic.store(Arc::new(2));
+        assert_eq!(*atomic.load(), 2);
+
+        let two = atomic.swap(one.clone());
+        assert_eq!(*two, 2);
+        assert!(Arc::ptr_eq(&atomic.load(), &one));
+
+        let three = Arc::new(3);
+        let three = atomic.compare_exchange(&two, three).unwrap_err();
+        let old = atomic.compare_exchange(&one, three.clone()).unwrap();
+        assert!(Arc::ptr_eq(&old, &one));
+        assert!(Arc::ptr_eq(&atomic.into_inner(), &three));
+    }
+
+    #[test]
+    fn load_store_concurrent() {
+        const THREADS: usize = 4;
+        const STEPS: usize = 4096;
+
+        let canary = AtomicUsize::new(0);
+        let atomic = AtomicArc::new(Arc::new(Canary(&canary)));
+        scope(|s| {
+            for _ in 0..THREADS {
+                let _ = s.spawn(|| {
+                    for _ in 0..STEPS {
+                        drop(atomic.load());
+                    }
+                });
+                let _ = s.spawn(|| {
+                    for i in 0..STEPS {
+                        let new = Arc::new(Canary(&canary));
+                        if i % 2 == 0 {
+                            atomic.store(new);
+                        } else {
+                            let current = atomic.load();
+                            let _ = atomic.compare_exchange(&current, new);
+                        }
+                    }
+                });
+            }
+        });
+        drop(atomic);
+
+        // Wait for the deferred decrements.
+        while canary.load(Relaxed) < THREADS * STEPS + 1 {
+            crossbeam_epoch::pin().flush();
+            yield_now();
+        }
+        assert_eq!(canary.load(Relaxed), THREADS * STEPS + 1);
+    }
+}
+
 mod correctness {
     use cs431_homework::Arc;
     use cs431_homework::test::loom::sync::atomic::AtomicUsize;
@@ -244,4 +549,22 @@ mod correctness {
             assert_eq!(canary.load(Relaxed), 1);
         })
     }
+
+    #[test]
+    /// upgrade success → data accesses → last drop → data drop
+    fn upgrade_drop_sync() {
+        model(|| {
+            let canary = AtomicUsize::new(0);
+            let arc = Arc::new(Canary(&canary));
+            let weak = Arc::downgrade(&arc);
+            let handle = thread::spawn(move || {
+                if let Some(arc) = weak.upgrade() {
+                    assert_eq!(unsafe { (*arc.0).load(Relaxed) }, 0);
+                }
+            });
+            drop(arc);
+            handle.join().unwrap();
+            assert_eq!(canary.load(Relaxed), 1);
+        })
+    }
 }
@@ -0,0 +1,216 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::mpsc::channel;
+use std::thread::{self, scope};
+
+use cs431_homework::BiasedArc;
+
+/// Counts the drops of the data. Each test uses its own counter.
+struct Canary(&'static AtomicUsize);
+
+impl Drop for Canary {
+    fn drop(&mut self) {
+        let _ = self.0.fetch_add(1, Relaxed);
+    }
+}
+
+#[test]
+fn owner_only() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    let mut x = BiasedArc::new(5);
+    assert_eq!(*BiasedArc::get_mut(&mut x).unwrap(), 5);
+    let y = x.clone();
+    assert_eq!(BiasedArc::count(&x), 2);
+    assert!(BiasedArc::ptr_eq(&x, &y));
+    assert!(BiasedArc::get_mut(&mut x).is_none());
+
+    *BiasedArc::make_mut(&mut x) += 1;
+    assert_eq!((*x, *y), (6, 5));
+    assert_eq!(BiasedArc::try_unwrap(x).unwrap(), 6);
+    assert_eq!(BiasedArc::try_unwrap(y).unwrap(), 5);
+
+    let x = BiasedArc::new(Canary(&DROPS));
+    let clones = (0..10).map(|_| x.clone()).collect::<Vec<_>>();
+    drop(x);
+    drop(clones);
+    assert_eq!(DROPS.load(Relaxed), 1);
+}
+
+#[test]
+fn clones_migrate() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 1000;
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    // The clones made by the owner are dropped by the other threads, and vice versa.
+    let (tx, rx) = channel();
+    let x = BiasedArc::new(Canary(&DROPS));
+    scope(|s| {
+        for _ in 0..THREADS {
+            let x = x.clone();
+            let tx = tx.clone();
+            s.spawn(move || {
+                for _ in 0..STEPS {
+                    tx.send(x.clone()).unwrap();
+                }
+            });
+        }
+        drop(tx);
+        for y in rx {
+            assert!(BiasedArc::ptr_eq(&x, &y));
+            drop(y.clone());
+        }
+    });
+    assert_eq!(BiasedArc::count(&x), 1);
+    assert_eq!(DROPS.load(Relaxed), 0);
+    drop(x);
+    assert_eq!(DROPS.load(Relaxed), 1);
+}
+
+#[test]
+fn owner_exits() {
+    const THREADS: usize = 8;
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    // The owner sends all its clones away and exits, so the biased count is merged on exit.
+    let clones = thread::spawn(|| {
+        let x = BiasedArc::new(Canary(&DROPS));
+        (0..THREADS).map(|_| x.clone()).collect::<Vec<_>>()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(DROPS.load(Relaxed), 0);
+    assert_eq!(BiasedArc::count(&clones[0]), THREADS);
+
+    scope(|s| {
+        for x in clones {
+            s.spawn(move || {
+                let y = x.clone();
+                drop(x);
+                assert_eq!(DROPS.load(Relaxed), 0);
+                drop(y);
+            });
+        }
+    });
+    assert_eq!(DROPS.load(Relaxed), 1);
+
+    // After the merge, `get_mut` succeeds on the other threads.
+    let mut x = thread::spawn(|| BiasedArc::new(5)).join().unwrap();
+    *BiasedArc::get_mut(&mut x).unwrap() += 1;
+    assert_eq!(BiasedArc::try_unwrap(x).unwrap(), 6);
+}
+
+#[test]
+fn dropped_by_others_before_owner_exits() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    let (tx, rx) = channel();
+    let (done_tx, done_rx) = channel();
+    let owner = thread::spawn(move || {
+        let x = BiasedArc::new(Canary(&DROPS));
+        let clones = (0..4).map(|_| x.clone()).collect::<Vec<_>>();
+        // The biased count doesn't drop to zero, since the clones are counted in it.
+        drop(x);
+        tx.send(clones).unwrap();
+        done_rx.recv().unwrap();
+        assert_eq!(DROPS.load(Relaxed), 0);
+    });
+
+    for x in rx.recv().unwrap() {
+        drop(x);
+    }
+    // The shared count is negative, and the object is queued to the owner. But the owner doesn't
+    // use `BiasedArc` anymore, so the data is dropped only when the owner exits.
+    assert_eq!(DROPS.load(Relaxed), 0);
+    done_tx.send(()).unwrap();
+    owner.join().unwrap();
+    assert_eq!(DROPS.load(Relaxed), 1);
+}
+
+#[test]
+fn moved_to_others() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    // The owner moves its only `BiasedArc` to another thread, which drops it. The owner merges the
+    // queued object at its next `BiasedArc` operation, long before it exits.
+    for i in 0..16 {
+        let x = BiasedArc::new(Canary(&DROPS));
+        thread::spawn(move || drop(x)).join().unwrap();
+        assert_eq!(DROPS.load(Relaxed), i);
+        drop(BiasedArc::new(()));
+        assert_eq!(DROPS.load(Relaxed), i + 1);
+    }
+
+    // Same for the clones made by the owner, merged when the owner clones another object.
+    let y = BiasedArc::new(0);
+    let x = BiasedArc::new(Canary(&DROPS));
+    let clones = (0..4).map(|_| x.clone()).collect::<Vec<_>>();
+    drop(x);
+    thread::spawn(move || drop(clones)).join().unwrap();
+    assert_eq!(DROPS.load(Relaxed), 16);
+    drop(y.clone());
+    assert_eq!(DROPS.load(Relaxed), 17);
+}
+
+#[test]
+fn nested() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    // When the owner exits, merging `outer` drops `inner` while `inner` is not merged yet.
+    let (tx, rx) = channel();
+    let (done_tx, done_rx) = channel();
+    let owner = thread::spawn(move || {
+        let inner = BiasedArc::new(Canary(&DROPS));
+        let outer = BiasedArc::new((inner, Canary(&DROPS)));
+        tx.send(outer.clone()).unwrap();
+        drop(outer);
+        done_rx.recv().unwrap();
+    });
+
+    drop(rx.recv().unwrap());
+    done_tx.send(()).unwrap();
+    owner.join().unwrap();
+    assert_eq!(DROPS.load(Relaxed), 2);
+}
+
+#[test]
+fn stress() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 10_000;
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    let (txs, rxs): (Vec<_>, Vec<_>) = (0..THREADS).map(|_| channel()).unzip();
+    let handles = rxs
+        .into_iter()
+        .enumerate()
+        .map(|(i, rx)| {
+            let txs = txs.clone();
+            thread::spawn(move || {
+                for step in 0..STEPS {
+                    // Each thread owns some objects and passes the clones around the ring.
+                    let x = if step % 16 == 0 {
+                        BiasedArc::new(Canary(&DROPS))
+                    } else {
+                        rx.recv().unwrap()
+                    };
+                    txs[(i + 1) % THREADS].send(x.clone()).unwrap();
+                    if step % 3 == 0 {
+                        txs[(i + 1) % THREADS].send(x).unwrap();
+                        let _ = rx.recv().unwrap();
+                    }
+                }
+                drop(txs);
+                for x in rx {
+                    drop(x);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(txs);
+    // Wait for the owners to exit and merge their objects.
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(DROPS.load(Relaxed), THREADS * STEPS.div_ceil(16));
+}
@@ -214,15 +214,178 @@ mod boc_merge_sort {
     }
 }
 
-mod basic_test {
+mod boc_readers {
+    //! Readers and writers on random cowns using [`boc`].
+
     use std::sync::Arc;
-    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::atomic::AtomicIsize;
+    use std::sync::atomic::Ordering::SeqCst;
 
     use crossbeam_channel::bounded;
     use cs431_homework::boc::{CownPtr, run_when};
     use cs431_homework::{tuple_list, when};
+    use rand::Rng;
+    use rand::seq::index::sample;
+
+    /// Marks the start of an access to a cown. The state is the number of readers, or -1 if there's
+    /// a writer.
+    fn enter(state: &AtomicIsize, read: bool) {
+        if read {
+            assert!(state.fetch_add(1, SeqCst) >= 0, "reader runs with a writer");
+        } else {
+            assert_eq!(
+                state.compare_exchange(0, -1, SeqCst, SeqCst),
+                Ok(0),
+                "writer runs with another behavior"
+            );
+        }
+    }
+
+    fn exit(state: &AtomicIsize, read: bool) {
+        if read {
+            let _ = state.fetch_sub(1, SeqCst);
+        } else {
+            state.store(0, SeqCst);
+        }
+    }
+
+    /// Each behavior reads one cown and writes to another, or only reads some cowns.
+    pub fn run_readers_writers(cown_cnt: usize, behavior_cnt: usize) {
+        assert!(cown_cnt >= 2);
+
+        let mut rng = rand::rng();
+        let cowns: Vec<_> = (0..cown_cnt).map(|_| CownPtr::new(0)).collect();
+        let states: Arc<[AtomicIsize]> = (0..cown_cnt).map(|_| AtomicIsize::new(0)).collect();
+        let mut writes = vec![0; cown_cnt];
+
+        for _ in 0..behavior_cnt {
+            let amount = rng.random_range(2..=cown_cnt.min(4));
+            let picked = sample(&mut rng, cown_cnt, amount);
+            let states = states.clone();
+            if rng.random_bool(0.5) {
+                let (r, w) = (picked.index(0), picked.index(1));
+                writes[w] += 1;
+                when!(cowns[r].read(), cowns[w]; _x, y; {
+                    enter(&states[r], true);
+                    enter(&states[w], false);
+                    *y += 1;
+                    exit(&states[w], false);
+                    exit(&states[r], true);
+                });
+            } else {
+                let idx = picked.into_vec();
+                let readers = idx.iter().map(|&i| cowns[i].read()).collect::<Vec<_>>();
+                run_when(readers, move |_| {
+                    for &i in &idx {
+                        enter(&states[i], true);
+                    }
+                    for &i in &idx {
+                        exit(&states[i], true);
+                    }
+                });
+            }
+        }
+
+        // All the writes are done before the final behavior.
+        let (finish_sender, finish_receiver) = bounded(0);
+        run_when(cowns, move |values| {
+            for (value, expected) in values.into_iter().zip(&writes) {
+                assert_eq!(*value, *expected);
+            }
+            finish_sender.send(()).unwrap();
+        });
+        finish_receiver.recv().unwrap();
+    }
+}
+
+mod boc_runtime {
+    //! Running behaviors on a [`BocRuntime`].
+
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+
+    use cs431_homework::boc::{BocRuntime, CownPtr, run_when};
+    use cs431_homework::{tuple_list, when};
+
+    /// Schedules behaviors on `runtime`, each of which increments a counter and then schedules
+    /// another behavior that increments the next counter. Returns the threads that ran them.
+    pub fn run_counters(
+        runtime: &BocRuntime,
+        counter_cnt: usize,
+        behavior_cnt: usize,
+    ) -> HashSet<ThreadId> {
+        let counters: Vec<_> = (0..counter_cnt).map(|_| CownPtr::new(0)).collect();
+        let threads = Arc::new(Mutex::new(HashSet::new()));
+
+        for i in 0..behavior_cnt {
+            let next = counters[(i + 1) % counter_cnt].clone();
+            let runtime_ = runtime.clone();
+            let threads = threads.clone();
+            when!(in runtime; counters[i % counter_cnt]; x; {
+                assert!(BocRuntime::current().ptr_eq(&runtime_));
+                let _ = threads.lock().unwrap().insert(thread::current().id());
+                *x += 1;
+                // This runs on the same runtime.
+                when!(next; y; *y += 1);
+            });
+        }
+        runtime.wait_idle();
+
+        // All the behaviors have finished, including the ones scheduled by the behaviors.
+        let total = Arc::new(AtomicUsize::new(0));
+        let total_ = total.clone();
+        runtime.run_when(counters, move |xs| {
+            total_.store(xs.into_iter().map(|x| *x).sum(), SeqCst);
+        });
+        runtime.wait_idle();
+        assert_eq!(total.load(SeqCst), 2 * behavior_cnt);
+
+        threads.lock().unwrap().clone()
+    }
+}
 
-    use crate::{boc_banking, boc_fibonacci, boc_merge_sort};
+mod boc_promise {
+    //! Chains of behaviors using [`Promise`].
+
+    use cs431_homework::boc::{CownPtr, Promise, run_when_promise};
+    use cs431_homework::{tuple_list, when_promise};
+
+    /// Runs `chain_cnt` chains of `len` behaviors, each of which adds the result of the previous
+    /// one to a shared counter. Returns the promises of the last results.
+    pub fn run_chains(chain_cnt: usize, len: usize) -> (CownPtr<usize>, Vec<Promise<usize>>) {
+        let counter = CownPtr::new(0);
+        let promises = (0..chain_cnt)
+            .map(|_| {
+                let mut promise = when_promise!(; ; 1);
+                for _ in 1..len {
+                    promise = promise.then(tuple_list!(counter.clone()), |value, (c, ())| {
+                        *c += 1;
+                        value + 1
+                    });
+                }
+                promise
+            })
+            .collect();
+        (counter, promises)
+    }
+}
+
+mod basic_test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use crossbeam_channel::bounded;
+    use cs431_homework::boc::{BocRuntime, CownPtr, Executor, Promise, run_when, run_when_promise};
+    use cs431_homework::{tuple_list, when, when_promise};
+
+    use crate::{
+        boc_banking, boc_fibonacci, boc_merge_sort, boc_promise, boc_readers, boc_runtime,
+    };
 
     #[test]
     fn message_passing() {
@@ -381,14 +544,218 @@ mod basic_test {
 
         recv_finish.recv().unwrap();
     }
+
+    #[test]
+    fn read_only() {
+        let c = CownPtr::new(0);
+        let (send_finish, recv_finish) = bounded(0);
+
+        // Readers see the writes scheduled before them, and writers wait for the readers.
+        for i in 0..20 {
+            when!(c; x; {
+                assert_eq!(*x, i);
+                *x += 1;
+            });
+            for _ in 0..3 {
+                when!(c.read(); x; assert_eq!(*x, i + 1));
+            }
+        }
+        when!(c.read(); x; {
+            assert_eq!(*x, 20);
+            send_finish.send(()).unwrap();
+        });
+
+        recv_finish.recv().unwrap();
+    }
+
+    #[test]
+    fn parallel_readers() {
+        const READERS: usize = 4;
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(READERS)
+            .build()
+            .unwrap();
+        let c = CownPtr::new(0);
+        let arrived = Arc::new(AtomicUsize::new(0));
+        let (send_finish, recv_finish) = bounded(0);
+
+        pool.install(move || {
+            when!(c; x; *x = 42);
+            // The readers wait for each other, so they should run in parallel.
+            for _ in 0..READERS {
+                let arrived = arrived.clone();
+                when!(c.read(); x; {
+                    assert_eq!(*x, 42);
+                    let _ = arrived.fetch_add(1, Ordering::SeqCst);
+                    let deadline = Instant::now() + Duration::from_secs(10);
+                    while arrived.load(Ordering::SeqCst) < READERS {
+                        assert!(Instant::now() < deadline, "readers are not run in parallel");
+                        thread::yield_now();
+                    }
+                });
+            }
+            let arrived = arrived.clone();
+            when!(c; x; {
+                assert_eq!(arrived.load(Ordering::SeqCst), READERS);
+                *x += 1;
+                send_finish.send(*x).unwrap();
+            });
+        });
+
+        assert_eq!(recv_finish.recv().unwrap(), 43);
+    }
+
+    #[test]
+    fn readers_writers() {
+        let (send_finish, recv_finish) = bounded(0);
+
+        rayon::spawn(move || {
+            boc_readers::run_readers_writers(8, 1000);
+            send_finish.send(()).unwrap();
+        });
+
+        recv_finish.recv().unwrap();
+    }
+
+    #[test]
+    fn runtime() {
+        const WORKERS: usize = 4;
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(WORKERS)
+            .build()
+            .unwrap();
+        let runtime = BocRuntime::new(pool);
+        let threads = boc_runtime::run_counters(&runtime, 8, 1000);
+        assert!(threads.len() <= WORKERS);
+    }
+
+    /// Runs each job on a new thread, so that a panic only ends that thread.
+    struct SpawnThread;
+
+    impl Executor for SpawnThread {
+        fn execute(&self, job: Box<dyn FnOnce() + Send>) {
+            let _ = thread::spawn(job);
+        }
+    }
+
+    #[test]
+    fn panicking_behavior() {
+        // The panicking behavior still finishes.
+        let runtime = BocRuntime::new(SpawnThread);
+        when!(in runtime; ; ; panic!("the behavior panics"));
+        runtime.wait_idle();
+
+        // The panicking behavior releases its cowns, so the later behaviors on them still run.
+        let c = CownPtr::new(0);
+        let d = CownPtr::new(0);
+        when!(in runtime; c, d; x, y; {
+            *x += 1;
+            *y += 1;
+            panic!("the behavior panics");
+        });
+        let (send, recv) = bounded(0);
+        when!(in runtime; c, d.read(); x, y; send.send(*x + *y).unwrap());
+        assert_eq!(recv.recv().unwrap(), 2);
+        runtime.wait_idle();
+    }
+
+    #[test]
+    fn promise() {
+        let c = CownPtr::new(1);
+        let d = CownPtr::new(10);
+
+        let p = when_promise!(c; x; {
+            *x += 1;
+            *x
+        });
+        assert_eq!(p.wait(), 2);
+
+        // The chained behaviors run after the previous ones with their results.
+        let p = when_promise!(c.read(); x; *x * 2)
+            .then(tuple_list!(d.clone()), |v, (y, ())| {
+                *y += v;
+                *y
+            })
+            .then((), |v, ()| v + 1);
+        assert_eq!(p.wait(), 15);
+
+        // The result is not available until the behavior finishes.
+        let (send, recv) = bounded(0);
+        let p = when_promise!(c; x; {
+            recv.recv().unwrap();
+            *x
+        });
+        let p = p.try_get().unwrap_err();
+        send.send(()).unwrap();
+        assert_eq!(p.wait(), 2);
+    }
+
+    #[test]
+    fn panicking_promise() {
+        let runtime = BocRuntime::new(SpawnThread);
+        let c = CownPtr::new(0);
+
+        // Waiting for the result of a panicking behavior panics.
+        let p: Promise<usize> = when_promise!(in runtime; ; ; panic!("the behavior panics"));
+        assert!(thread::spawn(move || p.wait()).join().is_err());
+
+        // The chained behavior doesn't run, and its promise panics as well.
+        let p: Promise<usize> = when_promise!(in runtime; ; ; panic!("the behavior panics"));
+        let p = p.then(tuple_list!(c.clone()), |v, (x, ())| {
+            *x += 1;
+            v
+        });
+        runtime.wait_idle();
+        assert!(thread::spawn(move || p.try_get()).join().is_err());
+        assert_eq!(when_promise!(in runtime; c; x; *x).wait(), 0);
+        runtime.wait_idle();
+    }
+
+    #[test]
+    fn promise_chains() {
+        let (counter, promises) = boc_promise::run_chains(8, 100);
+        for p in promises {
+            assert_eq!(p.wait(), 100);
+        }
+        let total = when_promise!(counter.read(); c; *c);
+        assert_eq!(total.wait(), 8 * 99);
+    }
+
+    #[test]
+    fn promise_runtime() {
+        let runtime = BocRuntime::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(4)
+                .build()
+                .unwrap(),
+        );
+        let runtime_ = runtime.clone();
+        let p = when_promise!(in runtime; ; ; 1);
+        runtime.wait_idle();
+
+        // The result is already available, so the chained behavior is scheduled right away.
+        let p = p.then((), move |v, ()| {
+            // It runs on the same runtime.
+            assert!(BocRuntime::current().ptr_eq(&runtime_));
+            v + 1
+        });
+        runtime.wait_idle();
+        assert_eq!(p.try_get().unwrap(), 2);
+    }
 }
 
 mod stress_test {
     use crossbeam_channel::{Receiver, Sender, bounded};
+    use cs431_homework::boc::{BocRuntime, run_when_promise};
     use cs431_homework::test::RandGen;
+    use cs431_homework::tuple_list;
     use rand::thread_rng;
 
-    use crate::{boc_banking, boc_fibonacci, boc_merge_sort};
+    use crate::{
+        boc_banking, boc_fibonacci, boc_merge_sort, boc_promise, boc_readers, boc_runtime,
+    };
 
     #[test]
     fn fibonacci() {
@@ -440,4 +807,56 @@ mod stress_test {
             reciever.recv().unwrap();
         }
     }
+
+    #[test]
+    fn readers_writers() {
+        let (send_finish, recv_finish) = bounded(0);
+
+        rayon::spawn(move || {
+            boc_readers::run_readers_writers(64, 100000);
+            send_finish.send(()).unwrap();
+        });
+
+        recv_finish.recv().unwrap();
+    }
+
+    #[test]
+    fn runtime() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(8)
+            .build()
+            .unwrap();
+        let runtime = BocRuntime::new(pool);
+        let _ = boc_runtime::run_counters(&runtime, 64, 100000);
+    }
+
+    #[test]
+    fn promise_chains() {
+        let (counter, promises) = boc_promise::run_chains(64, 2000);
+        for p in promises {
+            assert_eq!(p.wait(), 2000);
+        }
+        let total = run_when_promise(tuple_list!(counter), |(c, ())| *c);
+        assert_eq!(total.wait(), 64 * 1999);
+    }
+}
+
+mod thread_pool_runtime {
+    //! Runs the behaviors on the thread pool of `hello_server`.
+    //!
+    //! Not graded, as it needs the thread pool of the hello server homework.
+
+    use cs431_homework::boc::BocRuntime;
+    use cs431_homework::hello_server::ThreadPool;
+
+    use crate::boc_runtime;
+
+    #[test]
+    fn run_counters() {
+        const WORKERS: usize = 4;
+
+        let runtime = BocRuntime::new(ThreadPool::new(WORKERS));
+        let threads = boc_runtime::run_counters(&runtime, 8, 1000);
+        assert!(threads.len() <= WORKERS);
+    }
 }
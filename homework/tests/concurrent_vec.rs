@@ -0,0 +1,67 @@
+use std::collections::HashSet;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::*;
+use std::thread::scope;
+
+use cs431_homework::ConcurrentVec;
+
+#[test]
+fn smoke() {
+    let vec = ConcurrentVec::new();
+    assert!(vec.is_empty());
+    assert_eq!(vec.get(0), None);
+
+    for i in 0..4096 {
+        assert_eq!(vec.push(i * 2), i);
+    }
+    assert_eq!(vec.len(), 4096);
+    assert_eq!(vec.get(37), Some(&74));
+    assert_eq!(vec.get(4096), None);
+
+    let elements = vec.iter().map(|(i, &v)| (i, v)).collect::<Vec<_>>();
+    let expected = (0..4096).map(|i| (i, i * 2)).collect::<Vec<_>>();
+    assert_eq!(elements, expected);
+}
+
+#[test]
+fn push_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096 * 4;
+
+    let vec = ConcurrentVec::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let vec = &vec;
+            let _ = s.spawn(move || {
+                for i in 0..STEPS {
+                    let value = t * STEPS + i;
+                    let index = vec.push(value);
+                    assert_eq!(vec.get(index), Some(&value));
+                }
+            });
+        }
+    });
+
+    assert_eq!(vec.len(), THREADS * STEPS);
+    let values = vec.iter().map(|(_, &v)| v).collect::<HashSet<_>>();
+    assert_eq!(values, (0..THREADS * STEPS).collect());
+}
+
+#[test]
+fn drop_elements() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Tester;
+    impl Drop for Tester {
+        fn drop(&mut self) {
+            let _ = DROPS.fetch_add(1, Relaxed);
+        }
+    }
+
+    let vec = ConcurrentVec::new();
+    for _ in 0..2048 {
+        let _ = vec.push(Tester);
+    }
+    drop(vec);
+    assert_eq!(DROPS.load(Relaxed), 2048);
+}
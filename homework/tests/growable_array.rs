@@ -2,6 +2,7 @@
 
 use core::ops::Deref;
 use core::sync::atomic::Ordering::*;
+use std::thread::scope;
 
 use crossbeam_epoch::{Guard, Owned, Shared, pin};
 use cs431_homework::test::adt::map;
@@ -67,6 +68,55 @@ impl<V> ConcurrentMap<u32, V> for ArrayMap<V> {
             Err(_) => Err(()), // already removed
         }
     }
+
+    fn get_or_insert_with<'g, F>(&self, key: u32, mut f: F, guard: &'g Guard) -> &'g V
+    where
+        F: FnMut() -> V,
+    {
+        let slot = self.array.get(key as usize, guard);
+        let curr = slot.load(Acquire, guard);
+        if let Some(curr) = unsafe { curr.as_ref() } {
+            return curr;
+        }
+        let node = Owned::new(Node::new(f()));
+        match slot.compare_exchange(Shared::null(), node, AcqRel, Acquire, guard) {
+            Ok(n) => {
+                // SAFETY: As in `insert`.
+                unsafe { self.storage.push_node(n, guard) };
+                unsafe { n.deref() }
+            }
+            Err(e) => unsafe { e.current.deref() },
+        }
+    }
+
+    fn compare_and_replace<'g>(
+        &self,
+        key: &u32,
+        expected: &V,
+        new: V,
+        guard: &'g Guard,
+    ) -> Result<&'g V, V>
+    where
+        V: PartialEq,
+    {
+        let slot = self.array.get(*key as usize, guard);
+        let mut node = Owned::new(Node::new(new));
+        loop {
+            let curr = slot.load(Acquire, guard);
+            match unsafe { curr.as_ref() } {
+                Some(curr) if **curr == *expected => {}
+                _ => return Err(node.into_box().into_inner()),
+            }
+            match slot.compare_exchange(curr, node, AcqRel, Acquire, guard) {
+                Ok(n) => {
+                    // SAFETY: As in `insert`. The replaced node stays in `storage`.
+                    unsafe { self.storage.push_node(n, guard) };
+                    return Ok(unsafe { curr.deref() });
+                }
+                Err(e) => node = e.new,
+            }
+        }
+    }
 }
 
 mod stack {
@@ -179,6 +229,125 @@ fn smoke() {
     assert_eq!(list.lookup(&37, &guard), None);
 }
 
+/// Stores `index` at each of `indices`, and checks that they are stored.
+fn store_indices(array: &GrowableArray<usize>, indices: &[usize], guard: &Guard) {
+    for &index in indices {
+        let slot = array.get(index, guard);
+        if slot.load(Relaxed, guard).is_null() {
+            slot.store(Owned::new(index), Relaxed);
+        }
+        assert_eq!(unsafe { slot.load(Relaxed, guard).deref() }, &index);
+    }
+}
+
+/// Frees the elements at `indices`.
+fn free_indices(array: &GrowableArray<usize>, indices: &[usize], guard: &Guard) {
+    for &index in indices {
+        let element = array.get(index, guard).swap(Shared::null(), Relaxed, guard);
+        if !element.is_null() {
+            drop(unsafe { element.into_owned() });
+        }
+    }
+}
+
+#[test]
+fn shrink() {
+    let array = GrowableArray::new();
+    let guard = pin();
+
+    let indices = [0, 1, 1023, 1024, 4096, 1 << 20, (1 << 20) + 1, 1 << 30];
+    store_indices(&array, &indices, &guard);
+
+    for capacity in [1 << 30, (1 << 20) + 1, 1 << 20, 1025, 1024, 1] {
+        // Remove the elements that will be dropped with the segments.
+        let (kept, removed) = indices.split_at(indices.partition_point(|&i| i < capacity));
+        free_indices(&array, removed, &guard);
+        array.shrink_to(capacity, &guard);
+
+        store_indices(&array, kept, &guard);
+        for &index in removed {
+            assert!(array.get(index, &guard).load(Relaxed, &guard).is_null());
+        }
+    }
+
+    // Grow again after shrinking to empty.
+    free_indices(&array, &indices, &guard);
+    array.shrink_to(0, &guard);
+    store_indices(&array, &indices, &guard);
+    free_indices(&array, &indices, &guard);
+}
+
+#[test]
+fn shrink_with_readers() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 256;
+    const KEPT: usize = 2048;
+
+    let array = GrowableArray::new();
+    let kept = (0..KEPT).collect::<Vec<_>>();
+    store_indices(&array, &kept, &pin());
+
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                for _ in 0..STEPS {
+                    store_indices(&array, &kept, &pin());
+                }
+            });
+        }
+
+        // Repeatedly grow and shrink while the others are reading the kept indices.
+        let _ = s.spawn(|| {
+            for step in 0..STEPS {
+                let guard = pin();
+                let removed = [KEPT, 1 << 12, 1 << 20, 1 << (20 + step % 10)];
+                store_indices(&array, &removed, &guard);
+                free_indices(&array, &removed, &guard);
+                array.shrink_to(KEPT, &guard);
+            }
+        });
+    });
+
+    free_indices(&array, &kept, &pin());
+}
+
+#[test]
+fn shrink_with_writers() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 64;
+    const KEPT: usize = 2048;
+
+    let array = GrowableArray::new();
+    let kept = (0..KEPT).collect::<Vec<_>>();
+
+    for _ in 0..STEPS {
+        // Grow the empty array, so that the segments for the kept indices are not allocated yet.
+        let removed = [1 << 20];
+        store_indices(&array, &removed, &pin());
+        free_indices(&array, &removed, &pin());
+
+        // Lower the array while the others are storing to the kept indices.
+        scope(|s| {
+            for t in 0..THREADS {
+                let indices = &kept[t * KEPT / THREADS..(t + 1) * KEPT / THREADS];
+                let _ = s.spawn(|| store_indices(&array, indices, &pin()));
+            }
+            let _ = s.spawn(|| array.shrink_to(KEPT, &pin()));
+        });
+
+        // None of the stores are lost.
+        let guard = pin();
+        for &index in &kept {
+            assert_eq!(
+                unsafe { array.get(index, &guard).load(Relaxed, &guard).as_ref() },
+                Some(&index)
+            );
+        }
+        free_indices(&array, &kept, &guard);
+        array.shrink_to(0, &guard);
+    }
+}
+
 #[test]
 fn stress_sequential() {
     const STEPS: usize = 4096;
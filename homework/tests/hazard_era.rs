@@ -0,0 +1,123 @@
+//! The hazard eras are reserved in the slots of `hazard_pointer::{HazardBag, Shield}`, so these
+//! tests panic at their `todo!()`s until the hazard pointer homework is implemented. They are not
+//! graded.
+
+#![cfg(not(feature = "check-loom"))]
+
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering::*;
+use std::thread::scope;
+
+use cs431::lockfree;
+use cs431_homework::hazard_era::{HazardEra, Shield, alloc, collect, free, retire};
+use cs431_homework::test::adt::set;
+use cs431_homework::test::lockfree as lockfree_test;
+
+#[test]
+fn counter() {
+    const THREADS: usize = 4;
+    const ITER: usize = 1024 * 16;
+
+    let count = AtomicPtr::new(alloc(0usize));
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                let shield = Shield::default();
+                for _ in 0..ITER {
+                    let new_ptr = alloc(0);
+                    loop {
+                        let cur_ptr = shield.protect(&count);
+                        unsafe { *new_ptr = *cur_ptr + 1 };
+                        if count
+                            .compare_exchange(cur_ptr, new_ptr, AcqRel, Acquire)
+                            .is_ok()
+                        {
+                            unsafe { retire(cur_ptr) };
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    });
+    let cur = count.load(Acquire);
+    // exclusive access
+    assert_eq!(unsafe { *cur }, THREADS * ITER);
+    unsafe { free(cur) };
+}
+
+// The stack in `cs431::lockfree` with hazard eras.
+#[test]
+fn lockfree_stack() {
+    const THREADS: usize = 8;
+    const ITER: usize = 1024 * 16;
+
+    let stack = lockfree::Stack::<_, HazardEra>::default();
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                for i in 0..ITER {
+                    stack.push(i);
+                    assert!(stack.pop().is_some());
+                    collect();
+                }
+            });
+        }
+    });
+    assert!(stack.pop().is_none());
+}
+
+// The queue in `cs431::lockfree` with hazard eras.
+#[test]
+fn lockfree_queue() {
+    const THREADS: usize = 8;
+    const ITER: usize = 1024 * 32;
+
+    let queue = lockfree::Queue::<_, HazardEra>::default();
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                for i in 0..ITER {
+                    queue.push(i, &mut ());
+                    assert!(queue.try_pop(&mut ()).is_some());
+                }
+            });
+        }
+    });
+    assert!(queue.try_pop(&mut ()).is_none());
+}
+
+// The tests in `cs431::lockfree`, with HazardEra.
+#[test]
+fn lockfree_stack_suite() {
+    lockfree_test::stack_push_pop::<HazardEra>(10, 10_000);
+    lockfree_test::stack_take_all::<HazardEra>(10, 1_000);
+}
+
+#[test]
+fn lockfree_queue_suite() {
+    const COUNT: usize = 100_000;
+    lockfree_test::queue_spsc::<HazardEra>(COUNT);
+    lockfree_test::queue_spmc::<HazardEra>(3, COUNT);
+    lockfree_test::queue_mpmc::<HazardEra>(COUNT);
+}
+
+#[test]
+fn smr_list_stress_sequential() {
+    const STEPS: usize = 4096;
+    set::stress_sequential::<usize, lockfree::SmrList<usize, (), HazardEra>>(STEPS);
+}
+
+#[test]
+fn smr_list_stress_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+    set::stress_concurrent::<usize, lockfree::SmrList<usize, (), HazardEra>>(THREADS, STEPS);
+}
+
+#[test]
+fn smr_list_log_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+    set::log_concurrent::<usize, lockfree::SmrList<usize, (), HazardEra>>(THREADS, STEPS);
+}
@@ -1,9 +1,18 @@
 #[cfg(not(feature = "check-loom"))]
 use core::sync::atomic::{AtomicPtr, Ordering::*};
+use std::ptr;
 use std::thread::{scope, sleep};
 use std::time::Duration;
 
-use cs431_homework::hazard_pointer::{Shield, collect, retire};
+#[cfg(not(feature = "check-loom"))]
+use cs431::lockfree;
+#[cfg(not(feature = "check-loom"))]
+use cs431_homework::hazard_pointer::HazardPointer;
+use cs431_homework::hazard_pointer::{HpAtomic, HpOwned, Shield, collect, retire};
+#[cfg(not(feature = "check-loom"))]
+use cs431_homework::test::adt::set;
+#[cfg(not(feature = "check-loom"))]
+use cs431_homework::test::lockfree as lockfree_test;
 #[cfg(feature = "check-loom")]
 use loom::sync::atomic::{AtomicPtr, Ordering::*};
 use queue::Queue;
@@ -155,6 +164,152 @@ fn stack_queue() {
     assert!(stack.try_pop().is_none());
 }
 
+// Threads should be able to exit while the pointers they retired are still protected.
+#[test]
+fn exit_while_protected() {
+    const THREADS: usize = 8;
+
+    let atomics = (0..THREADS)
+        .map(|i| AtomicPtr::new(Box::leak(Box::new(i))))
+        .collect::<Vec<_>>();
+    let shields = atomics
+        .iter()
+        .map(|_| Shield::default())
+        .collect::<Vec<_>>();
+    let protected = atomics
+        .iter()
+        .zip(&shields)
+        .map(|(atomic, shield)| shield.protect(atomic) as usize)
+        .collect::<Vec<_>>();
+
+    scope(|s| {
+        for atomic in &atomics {
+            let _ = s.spawn(move || {
+                let ptr = atomic.swap(ptr::null_mut(), AcqRel);
+                unsafe { retire(ptr) };
+            });
+        }
+    });
+
+    // The retired pointers are still protected, so they must not have been freed.
+    for (i, ptr) in protected.into_iter().enumerate() {
+        assert_eq!(unsafe { *(ptr as *const usize) }, i);
+    }
+    drop(shields);
+    collect();
+}
+
+// Like `counter`, but with `HpAtomic`.
+#[test]
+fn counter_hp_atomic() {
+    const THREADS: usize = 4;
+    const ITER: usize = 1024 * 16;
+
+    let count = HpAtomic::new(0usize);
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                let mut shield = Shield::default();
+                for _ in 0..ITER {
+                    let mut new = Some(HpOwned::new(0));
+                    loop {
+                        let current = count.load(&mut shield);
+                        **new.as_mut().unwrap() = current.as_ref().unwrap() + 1;
+                        match count.compare_exchange(current, new) {
+                            Ok(()) => break,
+                            Err(returned) => new = returned,
+                        }
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(*count.into_owned().unwrap(), THREADS * ITER);
+}
+
+// Like `stack`, but with the stack in `cs431::lockfree`.
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn lockfree_stack() {
+    const THREADS: usize = 8;
+    const ITER: usize = 1024 * 16;
+
+    let stack = lockfree::Stack::<_, HazardPointer>::default();
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                for i in 0..ITER {
+                    stack.push(i);
+                    assert!(stack.pop().is_some());
+                    collect();
+                }
+            });
+        }
+    });
+    assert!(stack.pop().is_none());
+}
+
+// Like `queue`, but with the queue in `cs431::lockfree`.
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn lockfree_queue() {
+    const THREADS: usize = 8;
+    const ITER: usize = 1024 * 32;
+
+    let queue = lockfree::Queue::<_, HazardPointer>::default();
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                for i in 0..ITER {
+                    queue.push(i, &mut ());
+                    assert!(queue.try_pop(&mut ()).is_some());
+                    collect();
+                }
+            });
+        }
+    });
+}
+
+// The tests in `cs431::lockfree`, with HazardPointer.
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn lockfree_stack_suite() {
+    lockfree_test::stack_push_pop::<HazardPointer>(10, 10_000);
+    lockfree_test::stack_take_all::<HazardPointer>(10, 1_000);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn lockfree_queue_suite() {
+    const COUNT: usize = 100_000;
+    lockfree_test::queue_spsc::<HazardPointer>(COUNT);
+    lockfree_test::queue_spmc::<HazardPointer>(3, COUNT);
+    lockfree_test::queue_mpmc::<HazardPointer>(COUNT);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn smr_list_stress_sequential() {
+    const STEPS: usize = 4096;
+    set::stress_sequential::<usize, lockfree::SmrList<usize, (), HazardPointer>>(STEPS);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn smr_list_stress_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+    set::stress_concurrent::<usize, lockfree::SmrList<usize, (), HazardPointer>>(THREADS, STEPS);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn smr_list_log_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+    set::log_concurrent::<usize, lockfree::SmrList<usize, (), HazardPointer>>(THREADS, STEPS);
+}
+
 mod sync {
     use core::ptr;
 
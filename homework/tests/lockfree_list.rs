@@ -0,0 +1,51 @@
+use crossbeam_epoch as epoch;
+use cs431::lockfree::list::List;
+use cs431_homework::ConcurrentMap;
+use cs431_homework::test::adt::map;
+
+#[test]
+fn update() {
+    let list = List::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.get_or_insert_with(1, || 10, &guard), &10);
+    assert_eq!(list.get_or_insert_with(1, || 11, &guard), &10);
+
+    assert_eq!(
+        list.upsert(1, |v| v.map_or(0, |v| v + 1), &guard),
+        Some(&10)
+    );
+    assert_eq!(list.upsert(2, |v| v.map_or(0, |v| v + 1), &guard), None);
+    assert_eq!(list.lookup(&1, &guard), Some(&11));
+    assert_eq!(list.lookup(&2, &guard), Some(&0));
+
+    assert_eq!(list.compare_and_replace(&1, &10, 12, &guard), Err(12));
+    assert_eq!(list.compare_and_replace(&1, &11, 12, &guard), Ok(&11));
+    assert_eq!(list.compare_and_replace(&3, &11, 12, &guard), Err(12));
+    assert_eq!(list.lookup(&1, &guard), Some(&12));
+
+    assert_eq!(list.delete(&1, &guard), Ok(&12));
+    assert_eq!(list.lookup(&1, &guard), None);
+    assert_eq!(list.harris_michael_lookup(&2, &guard), Some(&0));
+    assert_eq!(list.harris_herlihy_shavit_lookup(&2, &guard), Some(&0));
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    map::stress_sequential::<_, _, List<usize, usize>>(STEPS);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<_, _, List<usize, usize>>(THREADS, STEPS);
+}
+
+#[test]
+fn log_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+    map::log_concurrent::<_, _, List<usize, usize>>(THREADS, STEPS);
+}
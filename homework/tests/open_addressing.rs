@@ -0,0 +1,133 @@
+#![feature(cfg_sanitize)]
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::*;
+
+use crossbeam_epoch as epoch;
+use cs431_homework::test::adt::map;
+use cs431_homework::{ConcurrentMap, OpenAddressingMap};
+
+#[test]
+fn smoke() {
+    let map = OpenAddressingMap::new();
+
+    let guard = epoch::pin();
+
+    assert_eq!(map.insert(37, 37, &guard), Ok(()));
+    assert_eq!(map.lookup(&42, &guard), None);
+    assert_eq!(map.lookup(&37, &guard), Some(&37));
+
+    assert_eq!(map.insert(42, 42, &guard), Ok(()));
+    assert_eq!(map.lookup(&42, &guard), Some(&42));
+    assert_eq!(map.lookup(&37, &guard), Some(&37));
+
+    assert_eq!(map.delete(&37, &guard), Ok(&37));
+    assert_eq!(map.lookup(&42, &guard), Some(&42));
+    assert_eq!(map.lookup(&37, &guard), None);
+
+    assert_eq!(map.delete(&37, &guard), Err(()));
+    assert_eq!(map.lookup(&42, &guard), Some(&42));
+    assert_eq!(map.lookup(&37, &guard), None);
+
+    assert_eq!(map.insert(37, 73, &guard), Ok(()));
+    assert_eq!(map.lookup(&37, &guard), Some(&73));
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn resize() {
+    const ITEMS: usize = 4096;
+
+    let map = OpenAddressingMap::new();
+
+    let guard = epoch::pin();
+    for i in 0..ITEMS {
+        assert_eq!(map.insert(i, i, &guard), Ok(()));
+    }
+    assert_eq!(map.len(), ITEMS);
+
+    // Fill the table with tombstones, so that it's resized to drop them.
+    for round in 0..4 {
+        for i in 0..ITEMS - 16 {
+            assert_eq!(map.delete(&i, &guard), Ok(&(i + round)));
+        }
+        for i in 0..ITEMS - 16 {
+            assert_eq!(map.insert(i, i + round + 1, &guard), Ok(()));
+        }
+    }
+    assert_eq!(map.len(), ITEMS);
+
+    for i in 0..ITEMS {
+        let expected = if i < ITEMS - 16 { i + 4 } else { i };
+        assert_eq!(map.lookup(&i, &guard), Some(&expected));
+    }
+}
+
+#[test]
+fn drop_values() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug)]
+    struct Tester;
+    impl Drop for Tester {
+        fn drop(&mut self) {
+            let _ = DROPS.fetch_add(1, Relaxed);
+        }
+    }
+
+    let map = OpenAddressingMap::new();
+    let guard = epoch::pin();
+    for i in 0..1024 {
+        assert!(map.insert(i, Tester, &guard).is_ok());
+    }
+    for i in 0..512 {
+        assert!(map.delete(&i, &guard).is_ok());
+    }
+    for i in 0..256 {
+        assert!(map.insert(i, Tester, &guard).is_ok());
+    }
+    // Rejected insertions drop their values right away. The values replaced by the insertions are
+    // retired, so they are not freed while `guard` is pinned, regardless of the other threads.
+    assert!(map.insert(0, Tester, &guard).is_err());
+    assert_eq!(DROPS.load(Relaxed), 1);
+
+    // Dropping the map drops the live values and the deleted values of the keys that are not
+    // inserted again right away.
+    drop(map);
+    assert_eq!(DROPS.load(Relaxed), 1 + 768 + 256);
+    drop(guard);
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    map::stress_sequential::<_, _, OpenAddressingMap<usize, usize>>(STEPS);
+}
+
+#[test]
+fn lookup_concurrent() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 4096;
+    map::lookup_concurrent::<_, _, OpenAddressingMap<usize, usize>>(THREADS, STEPS);
+}
+
+#[test]
+fn insert_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096 * 4;
+    map::insert_concurrent::<_, _, OpenAddressingMap<usize, usize>>(THREADS, STEPS);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = if cfg!(sanitize = "thread") { 4 } else { 16 };
+    const STEPS: usize = 4096 * if cfg!(sanitize = "thread") { 128 } else { 512 };
+    map::stress_concurrent::<_, _, OpenAddressingMap<usize, usize>>(THREADS, STEPS);
+}
+
+#[test]
+fn log_concurrent() {
+    const THREADS: usize = if cfg!(sanitize = "thread") { 4 } else { 16 };
+    const STEPS: usize = 4096 * if cfg!(sanitize = "thread") { 16 } else { 64 };
+    map::log_concurrent::<_, _, OpenAddressingMap<usize, usize>>(THREADS, STEPS);
+}
@@ -1,5 +1,9 @@
 #![feature(cfg_sanitize)]
 
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::thread::scope;
+
 use crossbeam_epoch as epoch;
 use cs431_homework::test::adt::map;
 use cs431_homework::{ConcurrentMap, SplitOrderedList};
@@ -27,36 +31,201 @@ pub fn smoke() {
     assert_eq!(list.lookup(&37, &guard), None);
 }
 
+#[test]
+fn resize() {
+    const ITEMS: usize = 4096;
+
+    let mut list = SplitOrderedList::with_capacity_and_load_factors(256, 2.0, 0.5);
+    assert!(list.capacity() >= 256);
+
+    let guard = epoch::pin();
+    for i in 0..ITEMS {
+        assert_eq!(list.insert(i, i, &guard), Ok(()));
+    }
+    assert!(list.capacity() >= ITEMS);
+
+    for i in 0..ITEMS - 16 {
+        assert_eq!(list.delete(&i, &guard), Ok(&i));
+    }
+    drop(guard);
+    list.shrink_to_fit();
+    assert_eq!(list.capacity(), 256);
+
+    let guard = epoch::pin();
+    for i in 0..ITEMS {
+        let expected = (i >= ITEMS - 16).then_some(&i);
+        assert_eq!(list.lookup(&i, &guard), expected);
+    }
+}
+
+#[test]
+fn update() {
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+
+    assert_eq!(list.get_or_insert_with(1, || 10, &guard), &10);
+    assert_eq!(list.get_or_insert_with(1, || 11, &guard), &10);
+
+    assert_eq!(
+        list.upsert(1, |v| v.map_or(0, |v| v + 1), &guard),
+        Some(&10)
+    );
+    assert_eq!(list.upsert(2, |v| v.map_or(0, |v| v + 1), &guard), None);
+    assert_eq!(list.lookup(&1, &guard), Some(&11));
+    assert_eq!(list.lookup(&2, &guard), Some(&0));
+
+    assert_eq!(list.compare_and_replace(&1, &10, 12, &guard), Err(12));
+    assert_eq!(list.compare_and_replace(&1, &11, 12, &guard), Ok(&11));
+    assert_eq!(list.compare_and_replace(&3, &11, 12, &guard), Err(12));
+    assert_eq!(list.lookup(&1, &guard), Some(&12));
+    assert_eq!(list.len(), 2);
+
+    assert_eq!(list.delete(&1, &guard), Ok(&12));
+    assert_eq!(list.lookup(&1, &guard), None);
+    assert_eq!(list.len(), 1);
+}
+
+#[test]
+fn iter_len_clear() {
+    const ITEMS: usize = 1024;
+
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+    assert!(list.is_empty());
+
+    for i in 0..ITEMS {
+        assert_eq!(list.insert(i, i * 2, &guard), Ok(()));
+    }
+    for i in (0..ITEMS).step_by(2) {
+        assert_eq!(list.delete(&i, &guard), Ok(&(i * 2)));
+    }
+    assert_eq!(list.len(), ITEMS / 2);
+
+    let items = list
+        .iter(&guard)
+        .map(|(&k, &v)| (k, v))
+        .collect::<HashMap<_, _>>();
+    let expected = (1..ITEMS).step_by(2).map(|i| (i, i * 2)).collect();
+    assert_eq!(items, expected);
+
+    list.clear(&guard);
+    assert!(list.is_empty());
+    assert_eq!(list.iter(&guard).next(), None);
+    assert_eq!(list.lookup(&1, &guard), None);
+}
+
+#[test]
+fn clear_concurrent_with_insert() {
+    const THREADS: usize = 4;
+    const ITEMS: usize = 4096;
+
+    let list = SplitOrderedList::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            let _ = s.spawn(move || {
+                let guard = epoch::pin();
+                for i in 0..ITEMS {
+                    assert_eq!(list.insert(t * ITEMS + i, i, &guard), Ok(()));
+                }
+            });
+        }
+        for _ in 0..THREADS {
+            list.clear(&epoch::pin());
+        }
+    });
+
+    let guard = epoch::pin();
+    assert_eq!(list.len(), list.iter(&guard).count());
+    list.clear(&guard);
+    assert!(list.is_empty());
+    assert_eq!(list.iter(&guard).next(), None);
+}
+
+/// Hasher that maps everything to one of the 256 hashes, to test hash collisions.
+#[derive(Debug, Default)]
+struct CollidingHasher(u64);
+
+impl Hasher for CollidingHasher {
+    fn finish(&self) -> u64 {
+        self.0 % 256
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = self.0.wrapping_mul(31).wrapping_add(byte.into());
+        }
+    }
+}
+
+type CollidingState = BuildHasherDefault<CollidingHasher>;
+
+#[test]
+fn collision() {
+    let list = SplitOrderedList::with_hasher(CollidingState::default());
+    let guard = epoch::pin();
+
+    let keys = (0..64).map(|i| i.to_string()).collect::<Vec<_>>();
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(list.insert(key.clone(), i, &guard), Ok(()));
+    }
+    assert_eq!(list.insert("0".to_string(), 64, &guard), Err(64));
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(list.lookup(key, &guard), Some(&i));
+    }
+    assert_eq!(list.lookup(&"64".to_string(), &guard), None);
+
+    for (i, key) in keys.iter().enumerate().step_by(2) {
+        assert_eq!(list.delete(key, &guard), Ok(&i));
+    }
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(list.lookup(key, &guard), (i % 2 == 1).then_some(&i));
+    }
+}
+
+#[test]
+fn sequential_collision() {
+    const STEPS: usize = 4096;
+    map::stress_sequential::<_, _, SplitOrderedList<usize, usize, CollidingState>>(STEPS);
+}
+
+#[test]
+fn concurrent_collision() {
+    const THREADS: usize = if cfg!(sanitize = "thread") { 4 } else { 8 };
+    const STEPS: usize = 4096 * 4;
+    map::stress_concurrent::<_, _, SplitOrderedList<usize, usize, CollidingState>>(THREADS, STEPS);
+}
+
 #[test]
 fn stress_sequential() {
     const STEPS: usize = 4096;
-    map::stress_sequential::<_, _, SplitOrderedList<usize>>(STEPS);
+    map::stress_sequential::<_, _, SplitOrderedList<usize, usize>>(STEPS);
 }
 
 #[test]
 fn lookup_concurrent() {
     const THREADS: usize = 4;
     const STEPS: usize = 4096;
-    map::lookup_concurrent::<_, _, SplitOrderedList<usize>>(THREADS, STEPS);
+    map::lookup_concurrent::<_, _, SplitOrderedList<usize, usize>>(THREADS, STEPS);
 }
 
 #[test]
 fn insert_concurrent() {
     const THREADS: usize = 8;
     const STEPS: usize = 4096 * 4;
-    map::insert_concurrent::<_, _, SplitOrderedList<usize>>(THREADS, STEPS);
+    map::insert_concurrent::<_, _, SplitOrderedList<usize, usize>>(THREADS, STEPS);
 }
 
 #[test]
 fn stress_concurrent() {
     const THREADS: usize = if cfg!(sanitize = "thread") { 4 } else { 16 };
     const STEPS: usize = 4096 * if cfg!(sanitize = "thread") { 128 } else { 512 };
-    map::stress_concurrent::<_, _, SplitOrderedList<usize>>(THREADS, STEPS);
+    map::stress_concurrent::<_, _, SplitOrderedList<usize, usize>>(THREADS, STEPS);
 }
 
 #[test]
 fn log_concurrent() {
     const THREADS: usize = if cfg!(sanitize = "thread") { 4 } else { 16 };
     const STEPS: usize = 4096 * if cfg!(sanitize = "thread") { 16 } else { 64 };
-    map::log_concurrent::<_, _, SplitOrderedList<usize>>(THREADS, STEPS);
+    map::log_concurrent::<_, _, SplitOrderedList<usize, usize>>(THREADS, STEPS);
 }
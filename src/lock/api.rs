@@ -67,7 +67,7 @@ impl<L: RawLock, T> Lock<L, T> {
     }
 
     /// Acquires the lock and dereferences the inner value.
-    pub fn lock(&self) -> LockGuard<L, T> {
+    pub fn lock(&self) -> LockGuard<'_, L, T> {
         let token = self.inner.lock();
         LockGuard {
             lock: self,
@@ -78,7 +78,7 @@ impl<L: RawLock, T> Lock<L, T> {
 
 impl<L: RawTryLock, T> Lock<L, T> {
     /// Tries to acquire the lock and dereferences the inner value.
-    pub fn try_lock(&self) -> Result<LockGuard<L, T>, ()> {
+    pub fn try_lock(&self) -> Result<LockGuard<'_, L, T>, ()> {
         self.inner.try_lock().map(|token| LockGuard {
             lock: self,
             token: ManuallyDrop::new(token),
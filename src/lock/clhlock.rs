@@ -48,7 +48,7 @@ unsafe impl RawLock for ClhLock {
         // SAFETY: `prev` is valid, as `self.tail` was valid at initialization and any `swap()` to
         // it by other `lock()`s. Hence, it points to valid memory as the thread that made `prev`
         // will not free it.
-        while unsafe { (*prev).locked.load(Acquire) } {
+        while unsafe { &*prev }.locked.load(Acquire) {
             backoff.snooze();
         }
 
@@ -60,7 +60,7 @@ unsafe impl RawLock for ClhLock {
     }
 
     unsafe fn unlock(&self, token: Self::Token) {
-        unsafe { (*token.0).locked.store(false, Release) };
+        unsafe { &*token.0 }.locked.store(false, Release);
     }
 }
 
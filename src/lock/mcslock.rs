@@ -54,12 +54,12 @@ unsafe impl RawLock for McsLock {
         // SAFETY: `prev` is valid, so is not the initial pointer. Hence, it is a pointer from
         // `swap()` by another thread's `lock()`, and that thread guarantees that `prev` will not be
         // freed until this store is complete.
-        unsafe { (*prev).next.store(node, Release) };
+        unsafe { &*prev }.next.store(node, Release);
 
         let backoff = Backoff::new();
         // SAFETY: `node` was made valid above. Since other threads will not free `node`, it still
         // points to valid memory.
-        while unsafe { (*node).locked.load(Acquire) } {
+        while unsafe { &*node }.locked.load(Acquire) {
             backoff.snooze();
         }
 
@@ -68,7 +68,7 @@ unsafe impl RawLock for McsLock {
 
     unsafe fn unlock(&self, token: Self::Token) {
         let node = token.0;
-        let mut next = unsafe { (*node).next.load(Acquire) };
+        let mut next = unsafe { &*node }.next.load(Acquire);
 
         if next.is_null() {
             if self
@@ -83,7 +83,7 @@ unsafe impl RawLock for McsLock {
             }
 
             while {
-                next = unsafe { (*node).next.load(Acquire) };
+                next = unsafe { &*node }.next.load(Acquire);
                 next.is_null()
             } {}
         }
@@ -91,7 +91,7 @@ unsafe impl RawLock for McsLock {
         // SAFETY: Since `next` is not null, the thread that made `next` has finished access to
         // `node`, hence we have unique access to it.
         drop(unsafe { Box::from_raw(node) });
-        unsafe { (*next).locked.store(false, Release) };
+        unsafe { &*next }.locked.store(false, Release);
     }
 }
 
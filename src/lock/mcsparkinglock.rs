@@ -55,10 +55,10 @@ unsafe impl RawLock for McsParkingLock {
         }
 
         // SAFETY: See safety of McsLock::lock().
-        unsafe { (*prev).next.store(node, Release) };
+        unsafe { &*prev }.next.store(node, Release);
 
         // SAFETY: See safety of McsLock::lock().
-        while unsafe { (*node).locked.load(Acquire) } {
+        while unsafe { &*node }.locked.load(Acquire) {
             thread::park();
         }
 
@@ -67,7 +67,7 @@ unsafe impl RawLock for McsParkingLock {
 
     unsafe fn unlock(&self, token: Self::Token) {
         let node = token.0;
-        let mut next = unsafe { (*node).next.load(Acquire) };
+        let mut next = unsafe { &*node }.next.load(Acquire);
 
         if next.is_null() {
             if self
@@ -81,7 +81,7 @@ unsafe impl RawLock for McsParkingLock {
             }
 
             while {
-                next = unsafe { (*node).next.load(Acquire) };
+                next = unsafe { &*node }.next.load(Acquire);
                 next.is_null()
             } {}
         }
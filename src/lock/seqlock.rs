@@ -154,7 +154,7 @@ impl<T> SeqLock<T> {
     }
 
     /// Acquires a writer's lock.
-    pub fn write_lock(&self) -> WriteGuard<T> {
+    pub fn write_lock(&self) -> WriteGuard<'_, T> {
         let seq = self.inner.write_lock();
         WriteGuard { lock: self, seq }
     }
@@ -162,7 +162,7 @@ impl<T> SeqLock<T> {
     /// # Safety
     ///
     /// All reads from the underlying data should be atomic.
-    pub unsafe fn read_lock(&self) -> ReadGuard<T> {
+    pub unsafe fn read_lock(&self) -> ReadGuard<'_, T> {
         let seq = self.inner.read_begin();
         ReadGuard { lock: self, seq }
     }
@@ -1,6 +1,6 @@
 //! Lock-free singly linked list.
 
-use core::cmp::Ordering::*;
+use core::cmp::Ordering::{self, *};
 use core::mem;
 use core::sync::atomic::Ordering::*;
 
@@ -71,6 +71,16 @@ impl<K, V> Clone for Cursor<'_, K, V> {
     }
 }
 
+/// Iterator over the entries of a [`List`] in the order of the list, created by [`List::iter`].
+///
+/// The iteration is not a snapshot: it skips the logically removed nodes, and may or may not see
+/// the concurrent insertions and deletions.
+#[derive(Debug)]
+pub struct Iter<'g, K, V> {
+    curr: Shared<'g, Node<K, V>>,
+    guard: &'g Guard,
+}
+
 impl<K, V> Node<K, V> {
     /// Creates a new node.
     pub fn new(key: K, value: V) -> Self {
@@ -81,16 +91,23 @@ impl<K, V> Node<K, V> {
         }
     }
 
+    /// Returns the key of the node.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns a mutable reference to the value of the node.
+    pub fn value_mut(&mut self) -> &mut V {
+        &mut self.value
+    }
+
     /// Extracts the inner value.
     pub fn into_value(self) -> V {
         self.value
     }
 }
 
-impl<'g, K, V> Cursor<'g, K, V>
-where
-    K: Ord,
-{
+impl<'g, K, V> Cursor<'g, K, V> {
     /// Creates a cursor.
     pub fn new(prev: &'g Atomic<Node<K, V>>, curr: Shared<'g, Node<K, V>>) -> Self {
         Self {
@@ -104,9 +121,17 @@ where
         self.curr
     }
 
-    /// Clean up a chain of logically removed nodes in each traversal.
+    /// Like [`find_harris`](Self::find_harris), but compares the keys of the nodes with the search
+    /// key by `cmp`, which returns the ordering of the given node's key relative to the search key.
+    ///
+    /// The nodes for which `cmp` returns `Less` should precede the others in the list. This is
+    /// useful when the keys are not totally ordered, e.g. when the nodes with the same hash are
+    /// only compared for equality.
     #[inline]
-    pub fn find_harris(&mut self, key: &K, guard: &'g Guard) -> Result<bool, ()> {
+    pub fn find_harris_by<F>(&mut self, mut cmp: F, guard: &'g Guard) -> Result<bool, ()>
+    where
+        F: FnMut(&K) -> Ordering,
+    {
         // Finding phase
         // - cursor.curr: first unmarked node w/ key >= search key (4)
         // - cursor.prev: the ref of .next in previous unmarked node (1 -> 2)
@@ -129,7 +154,7 @@ where
                 continue;
             }
 
-            match curr_node.key.cmp(key) {
+            match cmp(&curr_node.key) {
                 Less => {
                     self.curr = next;
                     self.prev = &curr_node.next;
@@ -167,57 +192,6 @@ where
         Ok(found)
     }
 
-    /// Clean up a single logically removed node in each traversal.
-    #[inline]
-    pub fn find_harris_michael(&mut self, key: &K, guard: &'g Guard) -> Result<bool, ()> {
-        loop {
-            debug_assert_eq!(self.curr.tag(), 0);
-
-            let Some(curr_node) = (unsafe { self.curr.as_ref() }) else {
-                return Ok(false);
-            };
-            let mut next = curr_node.next.load(Acquire, guard);
-
-            if next.tag() != 0 {
-                next = next.with_tag(0);
-                self.prev
-                    .compare_exchange(self.curr, next, Release, Relaxed, guard)
-                    .map_err(|_| ())?;
-                unsafe { guard.defer_destroy(self.curr) };
-                self.curr = next;
-                continue;
-            }
-
-            match curr_node.key.cmp(key) {
-                Less => {
-                    self.prev = &curr_node.next;
-                    self.curr = next;
-                }
-                Equal => return Ok(true),
-                Greater => return Ok(false),
-            }
-        }
-    }
-
-    /// Doesn't preform any cleanup. Gotta go fast. Doesn't fail.
-    #[inline]
-    pub fn find_harris_herlihy_shavit(&mut self, key: &K, guard: &'g Guard) -> Result<bool, ()> {
-        Ok(loop {
-            let Some(curr_node) = (unsafe { self.curr.as_ref() }) else {
-                break false;
-            };
-            match curr_node.key.cmp(key) {
-                Less => {
-                    // NOTE: unnecessary (this function is expected to be used only for `lookup`)
-                    self.prev = &curr_node.next;
-                    self.curr = curr_node.next.load(Acquire, guard);
-                }
-                Equal => break curr_node.next.load(Relaxed, guard).tag() == 0,
-                Greater => break false,
-            }
-        })
-    }
-
     /// Lookups the value at the current node.
     ///
     /// # Panics
@@ -278,12 +252,128 @@ where
 
         Ok(&curr_node.value)
     }
+
+    /// Replaces the current node with `node`, which should have the same key. Returns the value
+    /// of the replaced node, or `node` back if the current node is deleted or its next node has
+    /// changed.
+    ///
+    /// The current node is marked and linked to `node` with a single CAS on its `.next`, so the
+    /// replacement is atomic: `node` becomes reachable exactly when the current node is logically
+    /// removed. `node` is then reachable only via the removed node until the latter is unlinked,
+    /// which `find_harris` and `find_harris_michael` handle as usual.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current node is null.
+    #[inline]
+    pub fn replace(
+        &mut self,
+        mut node: Owned<Node<K, V>>,
+        guard: &'g Guard,
+    ) -> Result<&'g V, Owned<Node<K, V>>> {
+        let curr_node = unsafe { self.curr.as_ref() }.unwrap();
+
+        let next = curr_node.next.load(Acquire, guard);
+        if next.tag() != 0 {
+            return Err(node);
+        }
+        node.next = next.into();
+
+        // Release: to publish `node`, and the current view of the replacing thread on this mark.
+        // Acquire: as in `delete`.
+        let node = curr_node
+            .next
+            .compare_exchange(next, node.with_tag(1), AcqRel, Relaxed, guard)
+            .map_err(|e| e.new.with_tag(0))?
+            .with_tag(0);
+
+        if self
+            .prev
+            .compare_exchange(self.curr, node, Release, Relaxed, guard)
+            .is_ok()
+        {
+            // SAFETY: we are unlinker of curr. As the lifetime of the guard extends to the return
+            // value of the function, later access of curr_node is ok.
+            unsafe { guard.defer_destroy(self.curr) };
+        }
+        self.curr = node;
+
+        Ok(&curr_node.value)
+    }
 }
 
-impl<K, V> List<K, V>
+impl<'g, K, V> Cursor<'g, K, V>
 where
     K: Ord,
 {
+    /// Clean up a chain of logically removed nodes in each traversal.
+    #[inline]
+    pub fn find_harris(&mut self, key: &K, guard: &'g Guard) -> Result<bool, ()> {
+        self.find_harris_by(|curr_key| curr_key.cmp(key), guard)
+    }
+
+    /// Clean up a single logically removed node in each traversal.
+    #[inline]
+    pub fn find_harris_michael(&mut self, key: &K, guard: &'g Guard) -> Result<bool, ()> {
+        loop {
+            debug_assert_eq!(self.curr.tag(), 0);
+
+            let Some(curr_node) = (unsafe { self.curr.as_ref() }) else {
+                return Ok(false);
+            };
+            let mut next = curr_node.next.load(Acquire, guard);
+
+            if next.tag() != 0 {
+                next = next.with_tag(0);
+                self.prev
+                    .compare_exchange(self.curr, next, Release, Relaxed, guard)
+                    .map_err(|_| ())?;
+                unsafe { guard.defer_destroy(self.curr) };
+                self.curr = next;
+                continue;
+            }
+
+            match curr_node.key.cmp(key) {
+                Less => {
+                    self.prev = &curr_node.next;
+                    self.curr = next;
+                }
+                Equal => return Ok(true),
+                Greater => return Ok(false),
+            }
+        }
+    }
+
+    /// Doesn't preform any cleanup. Gotta go fast. Doesn't fail.
+    #[inline]
+    pub fn find_harris_herlihy_shavit(&mut self, key: &K, guard: &'g Guard) -> Result<bool, ()> {
+        Ok(loop {
+            let Some(curr_node) = (unsafe { self.curr.as_ref() }) else {
+                break false;
+            };
+            match curr_node.key.cmp(key) {
+                Less => {
+                    // NOTE: unnecessary (this function is expected to be used only for `lookup`)
+                    self.prev = &curr_node.next;
+                    self.curr = curr_node.next.load(Acquire, guard);
+                }
+                Equal => {
+                    let next = curr_node.next.load(Acquire, guard);
+                    if next.tag() == 0 {
+                        break true;
+                    }
+                    // A removed node may be followed by its replacement with the same key. See
+                    // `replace`.
+                    self.prev = &curr_node.next;
+                    self.curr = next.with_tag(0);
+                }
+                Greater => break false,
+            }
+        })
+    }
+}
+
+impl<K, V> List<K, V> {
     /// Creates a new list.
     pub fn new() -> Self {
         List {
@@ -297,6 +387,19 @@ where
         Cursor::new(&self.head, self.head.load(Acquire, guard))
     }
 
+    /// Returns an iterator over the entries of the list.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, K, V> {
+        Iter {
+            curr: self.head.load(Acquire, guard),
+            guard,
+        }
+    }
+}
+
+impl<K, V> List<K, V>
+where
+    K: Ord,
+{
     /// Finds a key using the given find strategy.
     #[inline]
     fn find<'g, F>(&'g self, key: &K, find: &F, guard: &'g Guard) -> (bool, Cursor<'g, K, V>)
@@ -396,3 +499,20 @@ where
         self.lookup(key, Cursor::find_harris_herlihy_shavit, guard)
     }
 }
+
+impl<'g, K, V> Iterator for Iter<'g, K, V> {
+    type Item = (&'g K, &'g V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: As in `find_harris`, the nodes reachable from the head, including the chains of
+        // logically removed nodes, are protected by the guard of lifetime `'g`.
+        while let Some(curr_node) = unsafe { self.curr.as_ref() } {
+            let next = curr_node.next.load(Acquire, self.guard);
+            self.curr = next.with_tag(0);
+            if next.tag() == 0 {
+                return Some((&curr_node.key, &curr_node.value));
+            }
+        }
+        None
+    }
+}
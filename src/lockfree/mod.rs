@@ -1,9 +1,18 @@
 //! Lock-free data structures.
 
 pub mod list;
+mod priority_queue;
 mod queue;
-mod stack;
+pub mod rcu;
+pub mod smr;
+mod smr_list;
+pub mod stack;
+mod tagged_stack;
 
 pub use list::List;
+pub use priority_queue::PriorityQueue;
 pub use queue::Queue;
+pub use rcu::RcuCell;
+pub use smr_list::SmrList;
 pub use stack::Stack;
+pub use tagged_stack::TaggedStack;
@@ -0,0 +1,514 @@
+//! Skip-list based lock-free priority queue.
+//!
+//! Usable with any number of producers and consumers.
+//!
+//! Shavit and Lotan.  Skiplist-Based Concurrent Priority Queues.  IPDPS 2000.
+//! <https://doi.org/10.1109/IPDPS.2000.845994>
+//!
+//! Alistarh, Kopinsky, Li and Shavit.  The SprayList: A Scalable Relaxed Priority Queue.  PPoPP
+//! 2015.  <https://doi.org/10.1145/2688500.2688523>
+//!
+//! The queue is a lock-free skip list in which `delete_min` logically deletes the first unmarked
+//! node in the bottom level by marking its `next` pointer, and then physically unlinks it from all
+//! levels.
+//!
+//! # Reclamation
+//!
+//! A node may be linked in several levels, and may be unlinked from them by different threads. So
+//! each node counts the references to it: one for each level it is linked in, and one for the
+//! inserting thread while it is building the tower. The node is destroyed with `crossbeam_epoch`
+//! when the count drops to zero.
+
+use core::cell::Cell;
+use core::cmp::Ordering::*;
+use core::hash::{BuildHasher, Hasher};
+use core::mem::ManuallyDrop;
+use core::sync::atomic::Ordering::*;
+use core::sync::atomic::{AtomicUsize, fence};
+use core::{array, ptr};
+use std::collections::HashSet;
+use std::collections::hash_map::RandomState;
+
+use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
+
+/// Maximum height of a tower.
+const MAX_HEIGHT: usize = 32;
+
+#[derive(Debug)]
+struct Node<K, V> {
+    key: K,
+    /// Taken out by the thread that logically deletes this node. Hence this is not dropped when
+    /// the node is destroyed.
+    value: ManuallyDrop<V>,
+    /// See the module-level documentation.
+    refs: AtomicUsize,
+    /// Mark: tag(), Tag: not needed
+    ///
+    /// The mark of `tower[0]` means that the node is logically deleted. The marks of the other
+    /// levels mean that the node should be unlinked from those levels.
+    tower: Box<[Atomic<Node<K, V>>]>,
+}
+
+impl<K, V> Node<K, V> {
+    fn new(key: K, value: V, height: usize) -> Self {
+        Self {
+            key,
+            value: ManuallyDrop::new(value),
+            refs: AtomicUsize::new(height + 1),
+            tower: (0..height).map(|_| Atomic::null()).collect(),
+        }
+    }
+
+    /// Drops `count` references to the node, and destroys it if there are no references left.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own `count` references to `node`.
+    unsafe fn release(node: Shared<'_, Self>, count: usize, guard: &Guard) {
+        if count == 0 {
+            return;
+        }
+        // SAFETY: `node` is valid as the caller owns a reference to it.
+        if unsafe { node.deref() }.refs.fetch_sub(count, Release) == count {
+            fence(Acquire);
+            // SAFETY: There are no references to `node`, so it is unreachable.
+            unsafe { guard.defer_destroy(node) };
+        }
+    }
+}
+
+/// Positions in each level of the skip list for a key.
+struct Position<'g, K, V> {
+    /// `left[level]` is the pointer to `right[level]` in the last node preceding the key.
+    left: [&'g Atomic<Node<K, V>>; MAX_HEIGHT],
+    /// `right[level]` is the first node not preceding the key.
+    right: [Shared<'g, Node<K, V>>; MAX_HEIGHT],
+}
+
+/// Lock-free priority queue.
+///
+/// Elements with smaller priorities are deleted first. Elements with equal priorities are deleted
+/// in an unspecified order.
+///
+/// In the default (strict) mode, `delete_min` is quiescently consistent: it may miss an element
+/// inserted concurrently, but otherwise returns an element with the smallest priority. In the
+/// relaxed mode, created by [`PriorityQueue::new_relaxed`], `delete_min` returns an element among
+/// the first few smallest ones, which reduces contention on the head of the queue.
+#[derive(Debug)]
+pub struct PriorityQueue<K, V> {
+    head: [Atomic<Node<K, V>>; MAX_HEIGHT],
+    /// Number of threads the spray is tuned for. `0` means the strict mode.
+    spray_threads: usize,
+}
+
+// `K` is read concurrently while searching, while `V` is only accessed by the inserting and the
+// deleting thread.
+unsafe impl<K: Send + Sync, V: Send> Sync for PriorityQueue<K, V> {}
+unsafe impl<K: Send + Sync, V: Send> Send for PriorityQueue<K, V> {}
+
+impl<K: Ord, V> Default for PriorityQueue<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+thread_local! {
+    /// State of the xorshift random number generator used for tower heights and sprays.
+    static RNG: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+}
+
+/// Returns a random number.
+fn random() -> u64 {
+    RNG.with(|rng| {
+        let mut x = rng.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        rng.set(x);
+        x
+    })
+}
+
+impl<K: Ord, V> PriorityQueue<K, V> {
+    /// Creates a new, empty priority queue.
+    pub fn new() -> Self {
+        Self {
+            head: array::from_fn(|_| Atomic::null()),
+            spray_threads: 0,
+        }
+    }
+
+    /// Creates a new, empty priority queue in the relaxed (SprayList) mode, tuned for the given
+    /// number of threads.
+    ///
+    /// In this mode, `delete_min` starts from a random node among roughly the first
+    /// `threads * log(threads)` nodes, so concurrent deletions rarely contend on the same node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is 0.
+    pub fn new_relaxed(threads: usize) -> Self {
+        assert!(threads > 0);
+        Self {
+            head: array::from_fn(|_| Atomic::null()),
+            spray_threads: threads,
+        }
+    }
+
+    /// Returns `true` if `node` precedes the position of `key` at `addr`.
+    ///
+    /// Nodes are ordered by key, and then by address to distinguish nodes with the same key.
+    fn precedes(node: &Node<K, V>, key: &K, addr: usize) -> bool {
+        node.key
+            .cmp(key)
+            .then((node as *const Node<K, V> as usize).cmp(&addr))
+            == Less
+    }
+
+    /// Finds the position of `key` at `addr`, unlinking the marked nodes on the way.
+    fn search<'g>(&'g self, key: &K, addr: usize, guard: &'g Guard) -> Position<'g, K, V> {
+        'retry: loop {
+            let mut position = Position {
+                left: array::from_fn(|level| &self.head[level]),
+                right: [Shared::null(); MAX_HEIGHT],
+            };
+            let mut pred = &self.head[..];
+
+            for level in (0..MAX_HEIGHT).rev() {
+                let mut curr = pred[level].load(Acquire, guard);
+                // `pred` is being unlinked from this level, so we can't insert after it.
+                if curr.tag() != 0 {
+                    continue 'retry;
+                }
+
+                while let Some(curr_node) = unsafe { curr.as_ref() } {
+                    let succ = curr_node.tower[level].load(Acquire, guard);
+
+                    if succ.tag() != 0 {
+                        // Help unlinking `curr` from this level.
+                        match pred[level].compare_exchange(
+                            curr,
+                            succ.with_tag(0),
+                            Release,
+                            Relaxed,
+                            guard,
+                        ) {
+                            Ok(_) => {
+                                // SAFETY: We unlinked `curr` from this level, so we own the
+                                // reference of this level.
+                                unsafe { Node::release(curr, 1, guard) };
+                                curr = succ.with_tag(0);
+                                continue;
+                            }
+                            Err(_) => continue 'retry,
+                        }
+                    }
+
+                    if !Self::precedes(curr_node, key, addr) {
+                        break;
+                    }
+                    pred = &curr_node.tower;
+                    curr = succ;
+                }
+
+                position.left[level] = &pred[level];
+                position.right[level] = curr;
+            }
+
+            return position;
+        }
+    }
+
+    /// Returns a random tower height. The height is `h` with probability `2^-h`.
+    fn random_height() -> usize {
+        (random().trailing_ones() as usize + 1).min(MAX_HEIGHT)
+    }
+
+    /// Inserts `value` with the given `priority`.
+    pub fn insert(&self, priority: K, value: V) {
+        let guard = &crossbeam_epoch::pin();
+        let height = Self::random_height();
+        let node = Owned::new(Node::new(priority, value, height)).into_shared(guard);
+        // SAFETY: `node` is valid as we hold a reference to it.
+        let node_ref = unsafe { node.deref() };
+        let key = &node_ref.key;
+        let addr = node.as_raw() as usize;
+
+        // Link the bottom level, which makes the node a part of the queue.
+        let mut position = loop {
+            let position = self.search(key, addr, guard);
+            node_ref.tower[0].store(position.right[0], Relaxed);
+            if position.left[0]
+                .compare_exchange(position.right[0], node, Release, Relaxed, guard)
+                .is_ok()
+            {
+                break position;
+            }
+        };
+
+        // Build the rest of the tower. Stop if the node is being deleted.
+        let mut linked = 1;
+        'build: while linked < height {
+            let level = linked;
+            loop {
+                let next = node_ref.tower[level].load(Relaxed, guard);
+                if next.tag() != 0 {
+                    break 'build;
+                }
+
+                let succ = position.right[level];
+                if next != succ
+                    && node_ref.tower[level]
+                        .compare_exchange(next, succ, Relaxed, Relaxed, guard)
+                        .is_err()
+                {
+                    continue;
+                }
+
+                if position.left[level]
+                    .compare_exchange(succ, node, Release, Relaxed, guard)
+                    .is_ok()
+                {
+                    linked += 1;
+                    break;
+                }
+
+                position = self.search(key, addr, guard);
+            }
+        }
+
+        // If the node has been deleted concurrently, its deleter may have missed the levels we
+        // linked after it marked them. Unlink them ourselves. The fence pairs with the one in
+        // `delete_min()`.
+        fence(SeqCst);
+        if node_ref.tower[0].load(Relaxed, guard).tag() != 0 {
+            let _ = self.search(key, addr, guard);
+        }
+
+        // SAFETY: We own the references of the levels we have not linked, and our own reference.
+        unsafe { Node::release(node, height - linked + 1, guard) };
+    }
+
+    /// Logically deletes the first unmarked node in the bottom level starting from `curr`.
+    fn claim_from<'g>(
+        &self,
+        mut curr: Shared<'g, Node<K, V>>,
+        guard: &'g Guard,
+    ) -> Option<Shared<'g, Node<K, V>>> {
+        loop {
+            let curr_node = unsafe { curr.as_ref() }?;
+            let next = curr_node.tower[0].load(Acquire, guard);
+            if next.tag() == 0 && curr_node.tower[0].fetch_or(1, AcqRel, guard).tag() == 0 {
+                return Some(curr);
+            }
+            curr = next.with_tag(0);
+        }
+    }
+
+    /// Returns a random node among roughly the first `threads * log(threads)` nodes, or null if
+    /// the spray falls off the queue.
+    ///
+    /// At each level from `log(threads) + 1` down to the bottom, the spray walks forward a random
+    /// number of steps in `0..=log(threads) + 1`.
+    fn spray<'g>(&'g self, threads: usize, guard: &'g Guard) -> Shared<'g, Node<K, V>> {
+        let log = (usize::BITS - threads.leading_zeros()) as usize;
+        let max_jump = log + 1;
+
+        let mut pred = &self.head[..];
+        let mut landed = Shared::null();
+        for level in (0..(log + 1).min(MAX_HEIGHT)).rev() {
+            let jumps = random() as usize % (max_jump + 1);
+            for _ in 0..jumps {
+                let curr = pred[level].load(Acquire, guard).with_tag(0);
+                let Some(curr_node) = (unsafe { curr.as_ref() }) else {
+                    break;
+                };
+                pred = &curr_node.tower;
+                landed = curr;
+            }
+        }
+        landed
+    }
+
+    /// Deletes an element with the smallest priority, and returns it with its priority.
+    ///
+    /// In the relaxed mode, the deleted element may not have the smallest priority. See
+    /// [`PriorityQueue`].
+    ///
+    /// Returns `None` if the queue is observed to be empty.
+    pub fn delete_min(&self) -> Option<(K, V)>
+    where
+        K: Clone,
+    {
+        let guard = &crossbeam_epoch::pin();
+
+        let mut claimed = None;
+        if self.spray_threads != 0 {
+            let landed = self.spray(self.spray_threads, guard);
+            claimed = self.claim_from(landed, guard);
+        }
+        // Fall back to the strict mode if the spray has fallen off the queue.
+        let node = match claimed {
+            Some(node) => node,
+            None => self.claim_from(self.head[0].load(Acquire, guard), guard)?,
+        };
+
+        // SAFETY: `node` is protected by `guard`.
+        let node_ref = unsafe { node.deref() };
+        // SAFETY: We have logically deleted the node, so we are the unique owner of the value.
+        let value = unsafe { ptr::read(&*node_ref.value) };
+        let key = node_ref.key.clone();
+
+        // Mark the upper levels so that the inserter stops building the tower, and unlink the node
+        // from all levels. The fence pairs with the one in `insert()`.
+        for level in (1..node_ref.tower.len()).rev() {
+            let _ = node_ref.tower[level].fetch_or(1, Relaxed, guard);
+        }
+        fence(SeqCst);
+        let _ = self.search(&node_ref.key, node.as_raw() as usize, guard);
+
+        Some((key, value))
+    }
+
+    /// Returns `true` if the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        let guard = &crossbeam_epoch::pin();
+        let mut curr = self.head[0].load(Acquire, guard);
+        while let Some(curr_node) = unsafe { curr.as_ref() } {
+            let next = curr_node.tower[0].load(Acquire, guard);
+            if next.tag() == 0 {
+                return false;
+            }
+            curr = next.with_tag(0);
+        }
+        true
+    }
+}
+
+impl<K, V> Drop for PriorityQueue<K, V> {
+    fn drop(&mut self) {
+        // SAFETY: since we have `&mut self`, there are no concurrent operations. Hence, we have
+        // sole ownership of the nodes that are still linked in some level.
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+
+        let mut nodes = HashSet::new();
+        for (level, head) in self.head.iter().enumerate() {
+            let mut curr = head.load(Relaxed, guard);
+            while let Some(curr_node) = unsafe { curr.as_ref() } {
+                let _ = nodes.insert(curr.as_raw());
+                curr = curr_node.tower[level].load(Relaxed, guard).with_tag(0);
+            }
+        }
+
+        for node in nodes {
+            // SAFETY: All linked nodes are valid, and each is destroyed once.
+            let mut node = unsafe { Box::from_raw(node.cast_mut()) };
+            // Nodes that are not logically deleted still own their value.
+            if node.tower[0].load(Relaxed, guard).tag() == 0 {
+                unsafe { ManuallyDrop::drop(&mut node.value) };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+    use std::thread::scope;
+
+    use super::*;
+
+    const THREADS: usize = 8;
+    const ELEMENTS_PER_THREAD: usize = 10_000;
+
+    #[test]
+    fn sequential() {
+        let queue = PriorityQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.delete_min(), None);
+
+        for i in [5, 1, 4, 1, 3, 9, 2, 6] {
+            queue.insert(i, i * 10);
+        }
+        assert!(!queue.is_empty());
+
+        for i in [1, 1, 2, 3, 4, 5, 6, 9] {
+            assert_eq!(queue.delete_min(), Some((i, i * 10)));
+        }
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn insert_then_delete_min() {
+        let queue = PriorityQueue::new();
+
+        scope(|scope| {
+            for t in 0..THREADS {
+                let queue = &queue;
+                scope.spawn(move || {
+                    for i in 0..ELEMENTS_PER_THREAD {
+                        let key = i * THREADS + t;
+                        queue.insert(key, key);
+                    }
+                });
+            }
+        });
+
+        for i in 0..THREADS * ELEMENTS_PER_THREAD {
+            assert_eq!(queue.delete_min(), Some((i, i)));
+        }
+        assert!(queue.is_empty());
+    }
+
+    fn concurrent(queue: PriorityQueue<usize, String>) {
+        let deleted = Mutex::new(Vec::new());
+
+        scope(|scope| {
+            for t in 0..THREADS {
+                let queue = &queue;
+                let deleted = &deleted;
+                scope.spawn(move || {
+                    let mut local = Vec::new();
+                    for i in 0..ELEMENTS_PER_THREAD {
+                        let key = i * THREADS + t;
+                        queue.insert(key, key.to_string());
+                        if i % 2 == 0 {
+                            let (key, value) = queue.delete_min().unwrap();
+                            assert_eq!(key.to_string(), value);
+                            local.push(key);
+                        }
+                    }
+                    deleted.lock().unwrap().extend(local);
+                });
+            }
+        });
+
+        let mut deleted = deleted.into_inner().unwrap();
+        while let Some((key, _)) = queue.delete_min() {
+            deleted.push(key);
+        }
+        deleted.sort_unstable();
+        assert!(deleted.into_iter().eq(0..THREADS * ELEMENTS_PER_THREAD));
+    }
+
+    #[test]
+    fn concurrent_strict() {
+        concurrent(PriorityQueue::new());
+    }
+
+    #[test]
+    fn concurrent_relaxed() {
+        concurrent(PriorityQueue::new_relaxed(THREADS));
+    }
+
+    #[test]
+    fn drop_remaining() {
+        let queue = PriorityQueue::new();
+        for i in 0..1000 {
+            queue.insert(i % 10, i.to_string());
+        }
+        for _ in 0..500 {
+            let _ = queue.delete_min().unwrap();
+        }
+    }
+}
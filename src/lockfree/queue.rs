@@ -5,19 +5,26 @@
 //! Michael and Scott.  Simple, Fast, and Practical Non-Blocking and Blocking Concurrent Queue
 //! Algorithms.  PODC 1996.  <http://dl.acm.org/citation.cfm?id=248106>
 
-use core::mem::{self, MaybeUninit};
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::AtomicPtr;
 use core::sync::atomic::Ordering::*;
 
-use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
 use crossbeam_utils::CachePadded;
 
+use super::smr::{Epoch, Smr};
+
 /// Michael-Scott queue.
+///
+/// Popped nodes are reclaimed with the scheme `R`.
 // The representation here is a singly-linked list, with a sentinel node at the front. In general
 // the `tail` pointer may lag behind the actual tail.
 #[derive(Debug)]
-pub struct Queue<T> {
-    head: CachePadded<Atomic<Node<T>>>,
-    tail: CachePadded<Atomic<Node<T>>>,
+pub struct Queue<T, R: Smr = Epoch> {
+    head: CachePadded<AtomicPtr<Node<T>>>,
+    tail: CachePadded<AtomicPtr<Node<T>>>,
+    _marker: PhantomData<R>,
 }
 
 #[derive(Debug)]
@@ -30,92 +37,106 @@ struct Node<T> {
     /// value until it gets popped out.
     data: MaybeUninit<T>,
 
-    next: Atomic<Node<T>>,
+    next: AtomicPtr<Node<T>>,
 }
 
 // Any particular `T` should never be accessed concurrently, so no need for `Sync`.
-unsafe impl<T: Send> Sync for Queue<T> {}
-unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send, R: Smr> Sync for Queue<T, R> {}
+unsafe impl<T: Send, R: Smr> Send for Queue<T, R> {}
 
-impl<T> Default for Queue<T> {
+impl<T, R: Smr> Default for Queue<T, R> {
     fn default() -> Self {
-        Self::new()
+        let sentinel = R::alloc(Node {
+            data: MaybeUninit::uninit(),
+            next: AtomicPtr::new(ptr::null_mut()),
+        });
+
+        Self {
+            head: CachePadded::new(AtomicPtr::new(sentinel)),
+            tail: CachePadded::new(AtomicPtr::new(sentinel)),
+            _marker: PhantomData,
+        }
     }
 }
 
 impl<T> Queue<T> {
     /// Create a new, empty queue.
     pub fn new() -> Self {
-        let sentinel = Box::into_raw(Box::new(Node {
-            data: MaybeUninit::uninit(),
-            next: Atomic::null(),
-        }))
-        .cast_const();
-
-        Self {
-            head: CachePadded::new(sentinel.into()),
-            tail: CachePadded::new(sentinel.into()),
-        }
+        Self::default()
     }
+}
 
+impl<T, R: Smr> Queue<T, R> {
     /// Adds `t` to the back of the queue.
-    pub fn push(&self, t: T, guard: &mut Guard) {
-        let mut new = Owned::new(Node {
+    pub fn push(&self, t: T, guard: &mut R::Guard) {
+        let new = R::alloc(Node {
             data: MaybeUninit::new(t),
-            next: Atomic::null(),
+            next: AtomicPtr::new(ptr::null_mut()),
         });
+        let shield = R::shield(guard);
 
         loop {
             // We push onto the tail, so we'll start optimistically by looking there first.
-            let tail = self.tail.load(Acquire, guard);
+            let tail = R::protect(&shield, &self.tail);
 
             // Attempt to push onto the `tail` snapshot; fails if `tail.next` has changed.
-            let tail_ref = unsafe { tail.deref() };
-            let next = tail_ref.next.load(Acquire, guard);
+            //
+            // SAFETY: `tail` is never null as it always points to a node, and it is protected by
+            // `shield`.
+            let tail_ref = unsafe { &*tail };
+            let next = tail_ref.next.load(Acquire);
 
             // If `tail` is not the actual tail, try to "help" by moving the tail pointer forward.
             if !next.is_null() {
-                let _ = self
-                    .tail
-                    .compare_exchange(tail, next, Release, Relaxed, guard);
+                let _ = self.tail.compare_exchange(tail, next, Release, Relaxed);
                 continue;
             }
 
             // looks like the actual tail; attempt to link at `tail.next`.
-            match tail_ref
+            if tail_ref
                 .next
-                .compare_exchange(Shared::null(), new, Release, Relaxed, guard)
+                .compare_exchange(ptr::null_mut(), new, Release, Relaxed)
+                .is_ok()
             {
-                Ok(new) => {
-                    // try to move the tail pointer forward.
-                    let _ = self
-                        .tail
-                        .compare_exchange(tail, new, Release, Relaxed, guard);
-                    break;
-                }
-                Err(e) => new = e.new,
+                // try to move the tail pointer forward.
+                let _ = self.tail.compare_exchange(tail, new, Release, Relaxed);
+                break;
             }
-            guard.repin();
+            R::repin(guard);
         }
     }
 
     /// Attempts to dequeue from the front.
     ///
     /// Returns `None` if the queue is observed to be empty.
-    pub fn try_pop(&self, guard: &mut Guard) -> Option<T> {
+    pub fn try_pop(&self, guard: &mut R::Guard) -> Option<T> {
+        let head_shield = R::shield(guard);
+        let next_shield = R::shield(guard);
+
         loop {
-            let head = self.head.load(Acquire, guard);
-            let next = unsafe { head.deref() }.next.load(Acquire, guard);
+            let head = R::protect(&head_shield, &self.head);
+            // SAFETY: `head` is never null as it always points to a node, and it is protected by
+            // `head_shield`.
+            let next = unsafe { &*head }.next.load(Acquire);
 
-            let next_ref = unsafe { next.as_ref() }?;
+            if next.is_null() {
+                return None;
+            }
+
+            // If `head` is not retired, then `next` is not retired either. So validating `head`
+            // also validates `next`.
+            R::set(&next_shield, next);
+            if R::validate(head, &self.head).is_err() {
+                continue;
+            }
+            // SAFETY: `next` is protected by `next_shield`.
+            let next_ref = unsafe { &*next };
 
             // Moves `tail` if it's stale. Relaxed load is enough because if tail == head, then the
             // messages for that node are already acquired.
-            let tail = self.tail.load(Relaxed, guard);
+            let tail = self.tail.load(Relaxed);
             if tail == head {
-                let _ = self
-                    .tail
-                    .compare_exchange(tail, next, Release, Relaxed, guard);
+                let _ = self.tail.compare_exchange(tail, next, Release, Relaxed);
             }
 
             // After the above load & CAS, the thread view ensures that the index of tail is greater
@@ -126,7 +147,7 @@ impl<T> Queue<T> {
             // albeit simpler.
             if self
                 .head
-                .compare_exchange(head, next, Release, Relaxed, guard)
+                .compare_exchange(head, next, Release, Relaxed)
                 .is_ok()
             {
                 // Since the above `compare_exchange()` succeeded, `head` is detached from `self` so
@@ -144,30 +165,34 @@ impl<T> Queue<T> {
                 // SAFETY: `head` is unreachable, and we no longer access `head`. We destroy `head`
                 // after the final access to `next` above to ensure that `next` is also destroyed
                 // after.
-                unsafe { guard.defer_destroy(head) };
+                unsafe { R::retire(guard, head) };
 
                 return Some(result);
             }
-            guard.repin();
+            R::repin(guard);
         }
     }
 }
 
-impl<T> Drop for Queue<T> {
+impl<T, R: Smr> Drop for Queue<T, R> {
     fn drop(&mut self) {
         // Destroy the sentinel node.
 
-        let sentinel = mem::take(&mut *self.head);
         // SAFETY: `pop()` never dropped the sentinel node so it is still valid.
-        let mut o_curr = unsafe { sentinel.into_owned() }.into_box().next;
+        let sentinel = *self.head.get_mut();
+        let mut o_curr = unsafe { (*sentinel).next.load(Relaxed) };
+        unsafe { R::free(sentinel) };
 
         // Destroy and deallocate `data` for the rest of the nodes.
 
         // SAFETY: All non-null nodes made were valid, and we have unique ownership via `&mut self`.
-        while let Some(curr) = unsafe { o_curr.try_into_owned() }.map(Owned::into_box) {
+        while !o_curr.is_null() {
+            let curr = unsafe { &mut *o_curr };
+            let next = *curr.next.get_mut();
             // SAFETY: Not sentinel node, so `data` is valid.
-            drop(unsafe { curr.data.assume_init() });
-            o_curr = curr.next;
+            unsafe { curr.data.assume_init_drop() };
+            unsafe { R::free(o_curr) };
+            o_curr = next;
         }
     }
 }
@@ -197,9 +222,9 @@ mod test {
         }
 
         pub fn is_empty(&self) -> bool {
-            let guard = &pin();
-            let head = self.queue.head.load(Acquire, guard);
-            let next = unsafe { head.deref() }.next.load(Acquire, guard);
+            let _guard = &pin();
+            let head = self.queue.head.load(Acquire);
+            let next = unsafe { &*head }.next.load(Acquire);
             next.is_null()
         }
 
@@ -0,0 +1,260 @@
+//! Read-copy-update (RCU) cell.
+//!
+//! McKenney and Slingwine.  Read-Copy Update: Using Execution History to Solve Concurrency
+//! Problems.  PDCS 1998.
+
+use core::sync::atomic::Ordering::*;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+
+use crossbeam_epoch::{Atomic, Guard, Owned};
+
+/// A cell for read-mostly data, e.g. configuration.
+///
+/// Readers get a reference to the current value without retrying or waiting. Writers build a new
+/// value from the current one and replace it, and the old value is destroyed after the readers
+/// that may be using it are unpinned.
+#[derive(Debug)]
+pub struct RcuCell<T> {
+    data: Atomic<T>,
+}
+
+impl<T> RcuCell<T> {
+    /// Creates a new cell with the given value.
+    pub fn new(value: T) -> Self {
+        Self {
+            data: Atomic::new(value),
+        }
+    }
+
+    /// Returns a reference to the current value. This is wait-free.
+    ///
+    /// The value may be replaced after this returns, but the reference stays valid while `guard`
+    /// is pinned.
+    pub fn load<'g>(&'g self, guard: &'g Guard) -> &'g T {
+        // SAFETY: `data` is never null, and the replaced values are destroyed only after `guard` is
+        // unpinned.
+        unsafe { self.data.load(Acquire, guard).deref() }
+    }
+
+    /// Returns a mutable reference to the value.
+    pub fn get_mut(&mut self) -> &mut T {
+        // SAFETY: We have unique ownership of the value via `&mut self`.
+        unsafe {
+            self.data
+                .load(Relaxed, crossbeam_epoch::unprotected())
+                .deref_mut()
+        }
+    }
+
+    /// Consumes the cell and returns the value.
+    pub fn into_inner(self) -> T {
+        // SAFETY: We have unique ownership of the value via `self`, and the cell is not dropped.
+        let value = unsafe {
+            self.data
+                .load(Relaxed, crossbeam_epoch::unprotected())
+                .into_owned()
+        };
+        core::mem::forget(self);
+        *value.into_box()
+    }
+}
+
+// The old values are destroyed by whichever thread collects the garbage, after the readers are
+// unpinned, as for `crossbeam_epoch::Guard::defer`.
+impl<T: Send + 'static> RcuCell<T> {
+    /// Replaces the value `v` with `f(v)`.
+    ///
+    /// If another writer replaces `v` concurrently, `f` is called again with the new value, so it
+    /// may be called more than once.
+    ///
+    /// The old value may be dropped after its borrows end, so the value should not borrow
+    /// anything:
+    ///
+    /// ```compile_fail
+    /// use cs431::lockfree::RcuCell;
+    ///
+    /// let value = 1usize;
+    /// let cell = RcuCell::new(&value);
+    /// cell.update(|&v| v);
+    /// ```
+    pub fn update<F: FnMut(&T) -> T>(&self, mut f: F) {
+        let guard = crossbeam_epoch::pin();
+        let mut old = self.data.load(Acquire, &guard);
+        loop {
+            // SAFETY: `data` is never null, and `old` is protected by `guard`.
+            let new = Owned::new(f(unsafe { old.deref() }));
+            match self
+                .data
+                .compare_exchange(old, new, AcqRel, Acquire, &guard)
+            {
+                Ok(_) => {
+                    // SAFETY: `old` is no longer reachable from the cell.
+                    unsafe { guard.defer_destroy(old) };
+                    return;
+                }
+                Err(e) => old = e.current,
+            }
+        }
+    }
+}
+
+/// Waits until the threads that are pinned when this is called are unpinned.
+///
+/// After [`RcuCell::update`] and then this, no readers are using the old value. This is not tied
+/// to a particular cell, and waits for all readers of the default collector.
+///
+/// # Panics
+///
+/// Panics if the current thread is pinned, as it would wait for itself.
+pub fn synchronize() {
+    assert!(
+        !crossbeam_epoch::is_pinned(),
+        "`synchronize` should not be called while pinned"
+    );
+
+    // Deferred functions are executed only after the threads that are pinned when they are
+    // deferred are unpinned.
+    let done = Arc::new(AtomicBool::new(false));
+    let guard = crossbeam_epoch::pin();
+    guard.defer({
+        let done = done.clone();
+        move || done.store(true, Release)
+    });
+    guard.flush();
+    drop(guard);
+
+    while !done.load(Acquire) {
+        crossbeam_epoch::pin().flush();
+        thread::yield_now();
+    }
+}
+
+impl<T> Drop for RcuCell<T> {
+    fn drop(&mut self) {
+        // SAFETY: We have unique ownership of the value via `&mut self`.
+        unsafe {
+            drop(
+                self.data
+                    .load(Relaxed, crossbeam_epoch::unprotected())
+                    .into_owned(),
+            )
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::*;
+    use std::thread::{self, scope};
+    use std::time::Duration;
+
+    use crossbeam_epoch::pin;
+
+    use super::*;
+
+    #[test]
+    fn smoke() {
+        let cell = RcuCell::new(37);
+        assert_eq!(*cell.load(&pin()), 37);
+
+        cell.update(|old| old + 5);
+        assert_eq!(*cell.load(&pin()), 42);
+
+        let mut cell = cell;
+        *cell.get_mut() += 1;
+        assert_eq!(cell.into_inner(), 43);
+    }
+
+    #[test]
+    fn update_concurrent() {
+        const THREADS: usize = 8;
+        const STEPS: usize = 10_000;
+
+        let cell = RcuCell::new(0);
+        scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| {
+                    for _ in 0..STEPS {
+                        cell.update(|old| old + 1);
+                    }
+                });
+            }
+        });
+        assert_eq!(cell.into_inner(), THREADS * STEPS);
+    }
+
+    #[test]
+    fn read_while_updating() {
+        const STEPS: usize = 10_000;
+
+        // Readers should never see a partially updated value.
+        let cell = RcuCell::new((0, 0));
+        scope(|s| {
+            s.spawn(|| {
+                for _ in 0..STEPS {
+                    cell.update(|&(a, b)| (a + 1, b + 1));
+                }
+            });
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut last = 0;
+                    while last < STEPS {
+                        let &(a, b) = cell.load(&pin());
+                        assert_eq!(a, b);
+                        assert!(a >= last);
+                        last = a;
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn synchronize_waits_for_readers() {
+        let cell = RcuCell::new(String::from("old"));
+        let loaded = AtomicBool::new(false);
+        let unpinned = AtomicBool::new(false);
+
+        scope(|s| {
+            s.spawn(|| {
+                let guard = pin();
+                let value = cell.load(&guard);
+                loaded.store(true, Release);
+                thread::sleep(Duration::from_millis(100));
+                assert_eq!(value, "old");
+                unpinned.store(true, Release);
+            });
+
+            while !loaded.load(Acquire) {
+                thread::yield_now();
+            }
+            cell.update(|_| String::from("new"));
+            synchronize();
+            assert!(unpinned.load(Acquire));
+            assert_eq!(cell.load(&pin()), "new");
+        });
+    }
+
+    #[test]
+    fn drop_values() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tester;
+        impl Drop for Tester {
+            fn drop(&mut self) {
+                let _ = DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let cell = RcuCell::new(Tester);
+        for _ in 0..100 {
+            cell.update(|_| Tester);
+        }
+        drop(cell);
+        synchronize();
+        assert_eq!(DROPS.load(Relaxed), 101);
+    }
+}
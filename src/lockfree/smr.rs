@@ -0,0 +1,137 @@
+//! Safe memory reclamation (SMR) schemes.
+//!
+//! Lock-free data structures cannot free a node right after unlinking it, as other threads may
+//! still be accessing it. An SMR scheme tracks which nodes may still be accessed, and defers their
+//! destruction until they are not. [`Stack`](super::Stack), [`Queue`](super::Queue) and
+//! [`SmrList`](super::SmrList) are generic over the scheme implementing [`Smr`], with [`Epoch`] as
+//! the default.
+//!
+//! The interface follows that of hazard pointers, as it is the most restrictive one: before
+//! dereferencing a pointer loaded from shared memory, one should protect it with a shield and
+//! validate that it is still reachable. Epoch-based reclamation protects all pointers loaded while
+//! pinned, so shields and validation are no-ops for [`Epoch`].
+//!
+//! NOTE: [`List`](super::List) is not generic over the scheme, as Harris's list traverses chains of
+//! logically deleted nodes, which cannot be validated with hazard pointers.
+//! [`SmrList`](super::SmrList) is its port that restarts the traversal instead.
+
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering::*;
+
+use crossbeam_epoch::{Guard, Shared};
+
+/// Safe memory reclamation scheme.
+///
+/// # Safety
+///
+/// Suppose a shield is [`set`](Smr::set) to a pointer `p`, and then `validate(p, src)` returns
+/// `Ok(())`. If "`src` still pointing to `p`" implies that `p` is not retired, then `p` must not be
+/// freed until the shield is set to another pointer or dropped, and the guard from which the shield
+/// was created is dropped or repinned.
+pub unsafe trait Smr {
+    /// Context of a single operation on a data structure.
+    type Guard;
+
+    /// Protection of a single pointer.
+    type Shield;
+
+    /// Starts an operation.
+    fn pin() -> Self::Guard;
+
+    /// Allows the reclamation of the pointers protected by `guard` so far, e.g. to ensure that
+    /// reclamation can make progress while retrying an operation.
+    ///
+    /// Shields created from `guard` should be set and validated again afterwards.
+    fn repin(guard: &mut Self::Guard) {
+        let _ = guard;
+    }
+
+    /// Creates a new shield that is valid while `guard` is.
+    fn shield(guard: &Self::Guard) -> Self::Shield;
+
+    /// Stores `pointer` to the shield.
+    fn set<T>(shield: &Self::Shield, pointer: *mut T);
+
+    /// Checks if `src` still points to `pointer`. If not, returns the current value.
+    ///
+    /// Synchronizes with the store of `pointer` to `src` like an acquire load does.
+    fn validate<T>(pointer: *mut T, src: &AtomicPtr<T>) -> Result<(), *mut T>;
+
+    /// Gets a protected pointer from `src`.
+    fn protect<T>(shield: &Self::Shield, src: &AtomicPtr<T>) -> *mut T {
+        let mut pointer = src.load(Relaxed);
+        loop {
+            Self::set(shield, pointer);
+            match Self::validate(pointer, src) {
+                Ok(()) => return pointer,
+                Err(new) => pointer = new,
+            }
+        }
+    }
+
+    /// Allocates a node on the heap.
+    ///
+    /// Schemes may allocate metadata together with the node, e.g. the era in which it is born.
+    fn alloc<T>(value: T) -> *mut T {
+        Box::into_raw(Box::new(value))
+    }
+
+    /// Frees a node immediately.
+    ///
+    /// # Safety
+    ///
+    /// `pointer` must be allocated by [`alloc`](Smr::alloc), and must not be accessed by any other
+    /// thread.
+    unsafe fn free<T>(pointer: *mut T) {
+        drop(unsafe { Box::from_raw(pointer) });
+    }
+
+    /// Retires a pointer, so that it is freed once it is no longer protected.
+    ///
+    /// # Safety
+    ///
+    /// * `pointer` must be removed from shared memory before calling this function, and must be
+    ///   allocated by [`alloc`](Smr::alloc).
+    /// * The same `pointer` should only be retired once.
+    /// * It should be safe to drop `*pointer` from any thread, e.g. `T: Send`, as it may be freed by
+    ///   another thread.
+    unsafe fn retire<T>(guard: &Self::Guard, pointer: *mut T);
+}
+
+/// Epoch-based reclamation with `crossbeam_epoch`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Epoch;
+
+unsafe impl Smr for Epoch {
+    type Guard = Guard;
+
+    type Shield = ();
+
+    fn pin() -> Guard {
+        crossbeam_epoch::pin()
+    }
+
+    fn repin(guard: &mut Guard) {
+        guard.repin();
+    }
+
+    fn shield(_guard: &Guard) {}
+
+    fn set<T>(_shield: &(), _pointer: *mut T) {}
+
+    fn validate<T>(_pointer: *mut T, src: &AtomicPtr<T>) -> Result<(), *mut T> {
+        // All pointers loaded while pinned are protected, so validation always succeeds. Still,
+        // synchronize with the store of the pointer so that the node can be dereferenced.
+        let _ = src.load(Acquire);
+        Ok(())
+    }
+
+    fn protect<T>(_shield: &(), src: &AtomicPtr<T>) -> *mut T {
+        src.load(Acquire)
+    }
+
+    unsafe fn retire<T>(guard: &Guard, pointer: *mut T) {
+        // SAFETY: `pointer` is allocated by `Box` in `alloc` as `Owned` is, and it is unreachable.
+        unsafe { guard.defer_destroy(Shared::from(pointer.cast_const())) };
+    }
+}
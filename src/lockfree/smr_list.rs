@@ -0,0 +1,309 @@
+//! Lock-free sorted singly linked list, generic over the memory reclamation scheme.
+//!
+//! [`List`](super::List) unlinks a whole chain of logically removed nodes at once, so it traverses
+//! the chain, which cannot be validated with hazard pointers. This list instead unlinks a single
+//! removed node at a time, and restarts the traversal from the head if it fails to do so. Then a
+//! node is reachable iff its predecessor's link still points to it unmarked, which can be
+//! validated.
+//!
+//! Michael. High Performance Dynamic Lock-Free Hash Tables and List-Based Sets. SPAA 2002.
+//! <https://dl.acm.org/doi/10.1145/564870.564881>
+
+use core::cmp::Ordering::*;
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering::*;
+
+use super::smr::{Epoch, Smr};
+
+/// Sorted singly linked list.
+///
+/// Removed nodes are reclaimed with the scheme `R`. Unlike [`List`](super::List), references to the
+/// values are not handed out, as they are protected only while the operation is running.
+#[derive(Debug)]
+pub struct SmrList<K, V, R: Smr = Epoch> {
+    head: AtomicPtr<Node<K, V>>,
+    _marker: PhantomData<R>,
+}
+
+#[derive(Debug)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    /// The lowest bit marks that this node is logically removed.
+    next: AtomicPtr<Node<K, V>>,
+}
+
+// `K` and `V` are accessed concurrently by the operations, and are dropped by the thread that frees
+// the node.
+unsafe impl<K: Send + Sync, V: Send + Sync, R: Smr> Sync for SmrList<K, V, R> {}
+unsafe impl<K: Send, V: Send, R: Smr> Send for SmrList<K, V, R> {}
+
+/// Returns the mark of `pointer`.
+fn tag<T>(pointer: *mut T) -> usize {
+    pointer.addr() & 1
+}
+
+/// Returns `pointer` with the mark set to `tag`.
+fn with_tag<T>(pointer: *mut T, tag: usize) -> *mut T {
+    pointer.map_addr(|addr| (addr & !1) | tag)
+}
+
+/// Position in the list, where `prev` points to `curr`.
+struct Cursor<K, V, R: Smr> {
+    /// Either the head, or the `next` of the node protected by `prev_shield`.
+    prev: *const AtomicPtr<Node<K, V>>,
+    /// Protected by `curr_shield`. Never marked.
+    curr: *mut Node<K, V>,
+    prev_shield: R::Shield,
+    curr_shield: R::Shield,
+    next_shield: R::Shield,
+}
+
+impl<K, V, R: Smr> Cursor<K, V, R> {
+    fn new(guard: &R::Guard) -> Self {
+        Self {
+            prev: ptr::null(),
+            curr: ptr::null_mut(),
+            prev_shield: R::shield(guard),
+            curr_shield: R::shield(guard),
+            next_shield: R::shield(guard),
+        }
+    }
+
+    fn prev(&self) -> &AtomicPtr<Node<K, V>> {
+        // SAFETY: `prev` is either the head of the list or in the node protected by `prev_shield`.
+        unsafe { &*self.prev }
+    }
+}
+
+impl<K, V, R: Smr> Default for SmrList<K, V, R> {
+    fn default() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> SmrList<K, V> {
+    /// Creates a new, empty list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: Ord, V, R: Smr> SmrList<K, V, R> {
+    /// Moves `cursor` to the first node whose key is not less than `key`, unlinking the logically
+    /// removed nodes on the way. Returns whether the key is found.
+    fn find(&self, key: &K, cursor: &mut Cursor<K, V, R>, guard: &R::Guard) -> bool {
+        'retry: loop {
+            cursor.prev = &self.head;
+            cursor.curr = R::protect(&cursor.curr_shield, &self.head);
+
+            loop {
+                // SAFETY: `curr` is protected by `curr_shield`.
+                let Some(curr_node) = (unsafe { cursor.curr.as_ref() }) else {
+                    return false;
+                };
+                let next = curr_node.next.load(Acquire);
+
+                // A node is marked before it is unlinked, and only an unmarked link is changed to
+                // unlink a node. So if `prev` still points to `curr` unmarked, then `curr` is not
+                // unlinked. If additionally `curr.next` still points to `next`, then `next` is not
+                // unlinked either.
+                R::set(&cursor.next_shield, with_tag(next, 0));
+                if R::validate(next, &curr_node.next).is_err()
+                    || R::validate(cursor.curr, cursor.prev()).is_err()
+                {
+                    continue 'retry;
+                }
+
+                if tag(next) != 0 {
+                    let next = with_tag(next, 0);
+                    if cursor
+                        .prev()
+                        .compare_exchange(cursor.curr, next, Release, Relaxed)
+                        .is_err()
+                    {
+                        continue 'retry;
+                    }
+                    // SAFETY: We unlinked `curr`, and only the unlinker retires a node.
+                    unsafe { R::retire(guard, cursor.curr) };
+                    cursor.curr = next;
+                    mem::swap(&mut cursor.curr_shield, &mut cursor.next_shield);
+                    continue;
+                }
+
+                match curr_node.key.cmp(key) {
+                    Less => {
+                        cursor.prev = &curr_node.next;
+                        cursor.curr = next;
+                        mem::swap(&mut cursor.prev_shield, &mut cursor.curr_shield);
+                        mem::swap(&mut cursor.curr_shield, &mut cursor.next_shield);
+                    }
+                    Equal => return true,
+                    Greater => return false,
+                }
+            }
+        }
+    }
+
+    /// Applies `f` to the value for `key`, if any.
+    pub fn lookup_with<U, F: FnOnce(&V) -> U>(&self, key: &K, f: F) -> Option<U> {
+        let guard = R::pin();
+        let mut cursor = Cursor::new(&guard);
+        if !self.find(key, &mut cursor, &guard) {
+            return None;
+        }
+        // SAFETY: `curr` is protected by `curr_shield`, and is not null as the key is found.
+        Some(f(&unsafe { &*cursor.curr }.value))
+    }
+
+    /// Returns `true` if the list contains `key`.
+    pub fn contains(&self, key: &K) -> bool {
+        self.lookup_with(key, |_| ()).is_some()
+    }
+
+    /// Inserts `value` for `key`. Returns `false` and drops `value` if `key` is already present.
+    pub fn insert(&self, key: K, value: V) -> bool {
+        let mut guard = R::pin();
+        let mut cursor = Cursor::new(&guard);
+        let node = R::alloc(Node {
+            key,
+            value,
+            next: AtomicPtr::new(ptr::null_mut()),
+        });
+
+        loop {
+            // SAFETY: `node` is not shared with other threads yet.
+            let node_ref = unsafe { &*node };
+            if self.find(&node_ref.key, &mut cursor, &guard) {
+                // SAFETY: `node` was never shared with other threads.
+                unsafe { R::free(node) };
+                return false;
+            }
+
+            node_ref.next.store(cursor.curr, Relaxed);
+            if cursor
+                .prev()
+                .compare_exchange(cursor.curr, node, Release, Relaxed)
+                .is_ok()
+            {
+                return true;
+            }
+            R::repin(&mut guard);
+        }
+    }
+
+    /// Removes `key` from the list. Returns whether `key` was present.
+    pub fn remove(&self, key: &K) -> bool {
+        let mut guard = R::pin();
+        let mut cursor = Cursor::new(&guard);
+
+        loop {
+            if !self.find(key, &mut cursor, &guard) {
+                return false;
+            }
+            // SAFETY: `curr` is protected by `curr_shield`, and is not null as the key is found.
+            let curr_node = unsafe { &*cursor.curr };
+
+            // Release: to release current view of the removing thread on this mark.
+            // Acquire: to ensure that if the latter CAS succeeds, then the thread that reads `next`
+            // through `prev` will be safe.
+            let next = curr_node.next.load(Acquire);
+            if tag(next) != 0
+                || curr_node
+                    .next
+                    .compare_exchange(next, with_tag(next, 1), AcqRel, Relaxed)
+                    .is_err()
+            {
+                // `curr` is removed by another thread, or a node is inserted after it.
+                R::repin(&mut guard);
+                continue;
+            }
+
+            if cursor
+                .prev()
+                .compare_exchange(cursor.curr, next, Release, Relaxed)
+                .is_ok()
+            {
+                // SAFETY: We unlinked `curr`, and only the unlinker retires a node.
+                unsafe { R::retire(&guard, cursor.curr) };
+            } else {
+                // Let `find` unlink it.
+                let _ = self.find(key, &mut cursor, &guard);
+            }
+            return true;
+        }
+    }
+}
+
+impl<K, V, R: Smr> Drop for SmrList<K, V, R> {
+    fn drop(&mut self) {
+        let mut o_curr = *self.head.get_mut();
+
+        // SAFETY: All nodes reachable from the head, including the marked ones, are not retired
+        // yet, and we have unique ownership via `&mut self`.
+        while !o_curr.is_null() {
+            let next = with_tag(*unsafe { &mut *o_curr }.next.get_mut(), 0);
+            unsafe { R::free(o_curr) };
+            o_curr = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread::scope;
+
+    use super::*;
+
+    #[test]
+    fn insert_remove() {
+        let list = SmrList::new();
+        assert!(!list.contains(&1));
+
+        assert!(list.insert(1, "a"));
+        assert!(list.insert(3, "c"));
+        assert!(list.insert(2, "b"));
+        assert!(!list.insert(2, "x"));
+        assert_eq!(list.lookup_with(&2, |v| *v), Some("b"));
+
+        assert!(list.remove(&2));
+        assert!(!list.remove(&2));
+        assert!(!list.contains(&2));
+        assert!(list.contains(&1));
+        assert!(list.contains(&3));
+    }
+
+    #[test]
+    fn concurrent() {
+        const THREADS: usize = 8;
+        const ITER: usize = 1_000;
+
+        let list = SmrList::new();
+
+        scope(|scope| {
+            for t in 0..THREADS {
+                let list = &list;
+                scope.spawn(move || {
+                    for i in 0..ITER {
+                        let key = i * THREADS + t;
+                        assert!(list.insert(key, key));
+                        assert_eq!(list.lookup_with(&key, |v| *v), Some(key));
+                        if i % 2 == 0 {
+                            assert!(list.remove(&key));
+                        }
+                    }
+                });
+            }
+        });
+
+        for key in 0..ITER * THREADS {
+            assert_eq!(list.contains(&key), key / THREADS % 2 == 1);
+        }
+    }
+}
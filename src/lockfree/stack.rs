@@ -1,15 +1,23 @@
-use core::mem::{self, MaybeUninit};
+//! Treiber's lock-free stack.
+
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
 use core::ptr;
+use core::sync::atomic::AtomicPtr;
 use core::sync::atomic::Ordering::*;
 
-use crossbeam_epoch::{Atomic, Owned, Shared};
+use crossbeam_epoch::Guard;
+
+use super::smr::{Epoch, Smr};
 
 /// Treiber's lock-free stack.
 ///
-/// Usable with any number of producers and consumers.
+/// Usable with any number of producers and consumers. Popped nodes are reclaimed with the scheme
+/// `R`.
 #[derive(Debug)]
-pub struct Stack<T> {
-    head: Atomic<Node<T>>,
+pub struct Stack<T, R: Smr = Epoch> {
+    head: AtomicPtr<Node<T>>,
+    _marker: PhantomData<R>,
 }
 
 #[derive(Debug)]
@@ -17,50 +25,46 @@ struct Node<T> {
     // MaybeUninit as the data may be taken out of the node.
     // TODO: fix the slides to sync with this.
     data: MaybeUninit<T>,
-    next: *const Node<T>,
+    next: *mut Node<T>,
 }
 
 // Any particular `T` should never be accessed concurrently, so no need for `Sync`.
-unsafe impl<T: Send> Send for Stack<T> {}
-unsafe impl<T: Send> Sync for Stack<T> {}
+unsafe impl<T: Send, R: Smr> Send for Stack<T, R> {}
+unsafe impl<T: Send, R: Smr> Sync for Stack<T, R> {}
 
-impl<T> Default for Stack<T> {
+impl<T, R: Smr> Default for Stack<T, R> {
     fn default() -> Self {
-        Self::new()
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            _marker: PhantomData,
+        }
     }
 }
 
 impl<T> Stack<T> {
     /// Creates a new, empty stack.
     pub fn new() -> Stack<T> {
-        Self {
-            head: Atomic::null(),
-        }
+        Self::default()
     }
+}
 
+impl<T, R: Smr> Stack<T, R> {
     /// Pushes a value on top of the stack.
     pub fn push(&self, t: T) {
-        let mut node = Owned::new(Node {
+        let node = R::alloc(Node {
             data: MaybeUninit::new(t),
-            next: ptr::null(),
+            next: ptr::null_mut(),
         });
 
-        // SAFETY: We don't dereference any pointers obtained from this guard.
-        let guard = unsafe { crossbeam_epoch::unprotected() };
-
-        let mut head = self.head.load(Relaxed, guard);
+        // We don't dereference any pointers loaded from `head`, so no need to protect them.
+        let mut head = self.head.load(Relaxed);
         loop {
-            node.next = head.as_raw();
+            // SAFETY: `node` is not shared with other threads yet.
+            unsafe { (*node).next = head };
 
-            match self
-                .head
-                .compare_exchange(head, node, Release, Relaxed, guard)
-            {
+            match self.head.compare_exchange(head, node, Release, Relaxed) {
                 Ok(_) => break,
-                Err(e) => {
-                    head = e.current;
-                    node = e.new;
-                }
+                Err(current) => head = current,
             }
         }
     }
@@ -69,16 +73,17 @@ impl<T> Stack<T> {
     ///
     /// Returns `None` if the stack is empty.
     pub fn pop(&self) -> Option<T> {
-        let mut guard = crossbeam_epoch::pin();
+        let mut guard = R::pin();
+        let shield = R::shield(&guard);
 
         loop {
-            let head = self.head.load(Acquire, &guard);
+            let head = R::protect(&shield, &self.head);
+            // SAFETY: `head` is protected by `shield`.
             let h = unsafe { head.as_ref() }?;
-            let next = Shared::from(h.next);
 
             if self
                 .head
-                .compare_exchange(head, next, Relaxed, Relaxed, &guard)
+                .compare_exchange(head, h.next, Relaxed, Relaxed)
                 .is_ok()
             {
                 // Since the above `compare_exchange()` succeeded, `head` is detached from
@@ -91,35 +96,150 @@ impl<T> Stack<T> {
                 let result = unsafe { h.data.assume_init_read() };
 
                 // SAFETY: `head` is unreachable, and we no longer access `head`.
-                unsafe { guard.defer_destroy(head) };
+                unsafe { R::retire(&guard, head) };
 
                 return Some(result);
             }
 
             // Repin to ensure the global epoch can make progress.
-            guard.repin();
+            R::repin(&mut guard);
         }
     }
 
     /// Returns `true` if the stack is empty.
     pub fn is_empty(&self) -> bool {
-        let guard = crossbeam_epoch::pin();
-        self.head.load(Acquire, &guard).is_null()
+        self.head.load(Acquire).is_null()
+    }
+
+    /// Atomically takes all elements out of the stack, leaving it empty.
+    ///
+    /// The returned iterator yields the elements from top to bottom. Elements pushed after this
+    /// call are not affected.
+    pub fn take_all(&self) -> IntoIter<T, R> {
+        IntoIter {
+            head: self.head.swap(ptr::null_mut(), Acquire),
+            _marker: PhantomData,
+        }
     }
 }
 
-impl<T> Drop for Stack<T> {
+// Traversing the stack is not supported by hazard pointers, as there is no way to validate that the
+// `next` of a protected node is not retired.
+impl<T> Stack<T> {
+    /// Returns the number of elements in the stack.
+    ///
+    /// The result is approximate in the presence of concurrent pushes and pops, as it counts the
+    /// elements reachable from the head at the time of the call. Takes time linear in the length.
+    pub fn len_approx(&self) -> usize {
+        let _guard = crossbeam_epoch::pin();
+        let mut curr = self.head.load(Acquire);
+        let mut len = 0;
+        // SAFETY: `_guard` protects all nodes reachable from the head when it was loaded, and
+        // `next` of a node is never changed after the node is pushed.
+        while let Some(node) = unsafe { curr.as_ref() } {
+            len += 1;
+            curr = node.next;
+        }
+        len
+    }
+}
+
+// `peek_with()` and `iter()` give out references to data that may be concurrently moved out by
+// `pop()`. The moved value is owned by the popping thread, which may drop it and deallocate the
+// resources it owns while the reference is still in use. Hence these are only provided for `Copy`
+// types, for which the bytes left in the node stay a valid value until the node is destroyed.
+impl<T: Copy + Sync> Stack<T> {
+    /// Applies `f` to the top element of the stack without popping it.
+    ///
+    /// Returns `None` if the stack is empty.
+    pub fn peek_with<U, F: FnOnce(&T) -> U>(&self, f: F, guard: &Guard) -> Option<U> {
+        let _ = guard;
+        let head = self.head.load(Acquire);
+        // SAFETY: `head` is protected by `guard`, and all nodes in the stack contain a value.
+        let h = unsafe { head.as_ref() }?;
+        Some(f(unsafe { h.data.assume_init_ref() }))
+    }
+
+    /// Returns an iterator over a snapshot of the stack, from top to bottom.
+    ///
+    /// The snapshot is taken when the head is loaded, so the iterator may yield elements that are
+    /// concurrently popped, and does not yield elements that are concurrently pushed.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, T> {
+        let _ = guard;
+        Iter {
+            curr: self.head.load(Acquire),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, R: Smr> Drop for Stack<T, R> {
     fn drop(&mut self) {
-        let mut o_curr = mem::take(&mut self.head);
+        let mut o_curr = *self.head.get_mut();
 
         // SAFETY: All non-null nodes made were valid, and we have unique ownership via `&mut self`.
-        while let Some(curr) = unsafe { o_curr.try_into_owned() }.map(Owned::into_box) {
-            drop(unsafe { curr.data.assume_init() });
-            o_curr = curr.next.into();
+        while !o_curr.is_null() {
+            let curr = unsafe { &mut *o_curr };
+            let next = curr.next;
+            unsafe { curr.data.assume_init_drop() };
+            unsafe { R::free(o_curr) };
+            o_curr = next;
         }
     }
 }
 
+/// Iterator over a snapshot of a [`Stack`], created by [`Stack::iter`].
+#[derive(Debug)]
+pub struct Iter<'g, T> {
+    curr: *const Node<T>,
+    _marker: PhantomData<&'g T>,
+}
+
+impl<'g, T> Iterator for Iter<'g, T> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<&'g T> {
+        // SAFETY: The nodes reachable from the head are protected by the guard of lifetime `'g`,
+        // and all of them contain a value.
+        let node = unsafe { self.curr.as_ref() }?;
+        self.curr = node.next;
+        Some(unsafe { node.data.assume_init_ref() })
+    }
+}
+
+/// Owning iterator over the elements taken from a [`Stack`], created by [`Stack::take_all`].
+#[derive(Debug)]
+pub struct IntoIter<T, R: Smr = Epoch> {
+    head: *mut Node<T>,
+    _marker: PhantomData<(T, R)>,
+}
+
+unsafe impl<T: Send, R: Smr> Send for IntoIter<T, R> {}
+unsafe impl<T: Sync, R: Smr> Sync for IntoIter<T, R> {}
+
+impl<T, R: Smr> Iterator for IntoIter<T, R> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        // SAFETY: The chain was detached from the stack by `take_all()`, so we uniquely own the
+        // values. Other threads may still be reading the nodes, so destroying them is deferred.
+        let node = self.head;
+        let node_ref = unsafe { node.as_ref() }?;
+        self.head = node_ref.next;
+
+        let result = unsafe { node_ref.data.assume_init_read() };
+        let guard = R::pin();
+        unsafe { R::retire(&guard, node) };
+        Some(result)
+    }
+}
+
+impl<T, R: Smr> Drop for IntoIter<T, R> {
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::thread::scope;
@@ -143,4 +263,49 @@ mod test {
 
         assert!(stack.is_empty());
     }
+
+    #[test]
+    fn peek_iter() {
+        let stack = Stack::new();
+        let guard = crossbeam_epoch::pin();
+        assert_eq!(stack.peek_with(|&x| x, &guard), None);
+        assert_eq!(stack.len_approx(), 0);
+
+        for i in 0..10 {
+            stack.push(i);
+        }
+
+        assert_eq!(stack.peek_with(|&x| x * 2, &guard), Some(18));
+        assert!(stack.iter(&guard).copied().eq((0..10).rev()));
+        assert_eq!(stack.len_approx(), 10);
+    }
+
+    #[test]
+    fn take_all() {
+        let stack = Stack::new();
+
+        scope(|scope| {
+            for t in 0..10 {
+                let stack = &stack;
+                scope.spawn(move || {
+                    for i in 0..1_000 {
+                        stack.push(t * 1_000 + i);
+                    }
+                });
+            }
+        });
+
+        let mut taken = stack.take_all().collect::<Vec<_>>();
+        assert!(stack.is_empty());
+
+        taken.sort_unstable();
+        assert!(taken.into_iter().eq(0..10_000));
+
+        // Remaining elements are dropped together with the iterator.
+        let stack = Stack::new();
+        stack.push(String::from("a"));
+        stack.push(String::from("b"));
+        let mut iter = stack.take_all();
+        assert_eq!(iter.next().as_deref(), Some("b"));
+    }
 }
@@ -0,0 +1,276 @@
+//! Treiber's stack with tagged pointers for ABA protection.
+//!
+//! Unlike [`Stack`](super::Stack), this stack does not rely on epoch-based reclamation. Instead,
+//! popped nodes are never returned to the allocator while the stack is alive: they are pushed onto
+//! an internal free list and reused by later pushes. Since a node's memory stays valid until the
+//! stack is dropped, a thread that reads `next` of a node that has concurrently been popped reads
+//! valid (if stale) memory. The ABA problem that arises from such reuse is prevented by packing a
+//! modification counter (tag) next to the pointer in the head word, so that a CAS fails if the
+//! head has been popped and re-pushed in the meantime.
+//!
+//! # Memory reuse
+//!
+//! - The memory held by the stack is bounded by its peak number of elements, not by its current
+//!   number of elements. Nodes are only deallocated when the stack is dropped.
+//! - A value is moved out of its node on `pop`, so the nodes on the free list never hold a value.
+//! - The tag is 16 bits wide. A thread that is preempted between its load and CAS of the head for
+//!   exactly a multiple of 2^16 modifications may still suffer from ABA. In practice, this is
+//!   considered unlikely enough.
+//! - The pointer is packed into the low 48 bits of the head word, which assumes that the user-space
+//!   address space fits in 48 bits (e.g. x86-64 and AArch64 with 4-level page tables). This is
+//!   checked when a node is allocated.
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::Ordering::*;
+use core::sync::atomic::{AtomicPtr, AtomicU64};
+
+/// Number of bits of a head word used for the pointer.
+const PTR_BITS: u32 = 48;
+
+/// Mask for the pointer part of a head word.
+const PTR_MASK: u64 = (1 << PTR_BITS) - 1;
+
+#[derive(Debug)]
+struct Node<T> {
+    // MaybeUninit as the data may be taken out of the node, and nodes in the free list are empty.
+    data: UnsafeCell<MaybeUninit<T>>,
+    // Atomic because a concurrent `pop()` may read `next` of a node that is being reused by
+    // another thread.
+    next: AtomicPtr<Node<T>>,
+}
+
+/// A Treiber stack of type-stable nodes, protected from ABA by a tag in the head word.
+///
+/// This is the building block of both the list of elements and the free list of [`TaggedStack`].
+#[derive(Debug)]
+struct TaggedHead<T> {
+    /// Packed (pointer, tag) pair. See [`TaggedHead::pack`].
+    head: AtomicU64,
+    _marker: PhantomData<*mut Node<T>>,
+}
+
+impl<T> TaggedHead<T> {
+    const fn new() -> Self {
+        Self {
+            head: AtomicU64::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Packs a pointer and a tag into a head word.
+    fn pack(ptr: *mut Node<T>, tag: u16) -> u64 {
+        (ptr as u64 & PTR_MASK) | (u64::from(tag) << PTR_BITS)
+    }
+
+    /// Unpacks a head word into a pointer and a tag.
+    fn unpack(word: u64) -> (*mut Node<T>, u16) {
+        ((word & PTR_MASK) as *mut Node<T>, (word >> PTR_BITS) as u16)
+    }
+
+    /// Pushes a node.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a valid node that is not in any list, and must stay valid while `self` is
+    /// alive.
+    unsafe fn push(&self, node: *mut Node<T>) {
+        let mut word = self.head.load(Relaxed);
+        loop {
+            let (head, tag) = Self::unpack(word);
+            // SAFETY: `node` is valid and not shared with other threads, except for the stale
+            // reads of `next` in `pop()`, which are atomic.
+            unsafe { (*node).next.store(head, Relaxed) };
+
+            match self.head.compare_exchange(
+                word,
+                Self::pack(node, tag.wrapping_add(1)),
+                Release,
+                Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(current) => word = current,
+            }
+        }
+    }
+
+    /// Pops a node. The returned node is uniquely owned by the caller.
+    fn pop(&self) -> Option<*mut Node<T>> {
+        let mut word = self.head.load(Acquire);
+        loop {
+            let (head, tag) = Self::unpack(word);
+            if head.is_null() {
+                return None;
+            }
+
+            // SAFETY: Nodes are never deallocated while `self` is alive, so `head` is valid even if
+            // it has been popped concurrently. In that case `next` may be stale, but then the tag
+            // must have changed and the CAS below fails.
+            let next = unsafe { (*head).next.load(Relaxed) };
+
+            match self.head.compare_exchange(
+                word,
+                Self::pack(next, tag.wrapping_add(1)),
+                Acquire,
+                Acquire,
+            ) {
+                Ok(_) => return Some(head),
+                Err(current) => word = current,
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        Self::unpack(self.head.load(Acquire)).0.is_null()
+    }
+
+    /// Detaches the whole chain of nodes. Requires unique access.
+    fn take(&mut self) -> *mut Node<T> {
+        let (head, _) = Self::unpack(*self.head.get_mut());
+        *self.head.get_mut() = 0;
+        head
+    }
+}
+
+/// Treiber's lock-free stack that uses tagged pointers instead of epochs to prevent ABA.
+///
+/// Usable with any number of producers and consumers, without a global garbage collector. See the
+/// [module-level documentation](self) for the memory reuse semantics.
+#[derive(Debug)]
+pub struct TaggedStack<T> {
+    /// Nodes that contain values.
+    head: TaggedHead<T>,
+    /// Empty nodes that can be reused by `push()`.
+    free: TaggedHead<T>,
+}
+
+// Any particular `T` should never be accessed concurrently, so no need for `Sync`.
+unsafe impl<T: Send> Send for TaggedStack<T> {}
+unsafe impl<T: Send> Sync for TaggedStack<T> {}
+
+impl<T> Default for TaggedStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TaggedStack<T> {
+    /// Creates a new, empty stack.
+    pub const fn new() -> TaggedStack<T> {
+        Self {
+            head: TaggedHead::new(),
+            free: TaggedHead::new(),
+        }
+    }
+
+    /// Pushes a value on top of the stack.
+    ///
+    /// Reuses a node from the free list if possible, and allocates a new one otherwise.
+    pub fn push(&self, t: T) {
+        let node = self.free.pop().unwrap_or_else(|| {
+            let node = Box::into_raw(Box::new(Node {
+                data: UnsafeCell::new(MaybeUninit::uninit()),
+                next: AtomicPtr::new(ptr::null_mut()),
+            }));
+            assert_eq!(
+                node as u64 & !PTR_MASK,
+                0,
+                "node address does not fit in {PTR_BITS} bits"
+            );
+            node
+        });
+
+        // SAFETY: `node` is freshly allocated or uniquely owned after being popped from the free
+        // list, and nodes in the free list are empty.
+        unsafe { (*(*node).data.get()).write(t) };
+
+        // SAFETY: `node` is valid, not in any list, and deallocated only when `self` is dropped.
+        unsafe { self.head.push(node) };
+    }
+
+    /// Attempts to pop the top element from the stack.
+    ///
+    /// Returns `None` if the stack is empty.
+    pub fn pop(&self) -> Option<T> {
+        let node = self.head.pop()?;
+
+        // SAFETY: We uniquely own `node` after popping it, and the nodes in `head` always contain
+        // a value. We take the ownership of the value, making `node` empty.
+        let result = unsafe { (*(*node).data.get()).assume_init_read() };
+
+        // SAFETY: `node` is empty and not in any list.
+        unsafe { self.free.push(node) };
+
+        Some(result)
+    }
+
+    /// Returns `true` if the stack is empty.
+    pub fn is_empty(&self) -> bool {
+        self.head.is_empty()
+    }
+}
+
+impl<T> Drop for TaggedStack<T> {
+    fn drop(&mut self) {
+        // SAFETY: All non-null nodes made were valid, and we have unique ownership via `&mut self`.
+        let mut o_curr = self.head.take();
+        while !o_curr.is_null() {
+            let curr = unsafe { Box::from_raw(o_curr) };
+            drop(unsafe { curr.data.into_inner().assume_init() });
+            o_curr = curr.next.into_inner();
+        }
+
+        // The nodes in the free list are empty.
+        let mut o_curr = self.free.take();
+        while !o_curr.is_null() {
+            let curr = unsafe { Box::from_raw(o_curr) };
+            o_curr = curr.next.into_inner();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread::scope;
+
+    use super::*;
+
+    #[test]
+    fn push() {
+        let stack = TaggedStack::new();
+
+        scope(|scope| {
+            for _ in 0..10 {
+                scope.spawn(|| {
+                    for i in 0..10_000 {
+                        stack.push(i);
+                        assert!(stack.pop().is_some());
+                    }
+                });
+            }
+        });
+
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn reuse() {
+        let stack = TaggedStack::new();
+
+        for i in 0..100 {
+            stack.push(i);
+        }
+        for i in (0..100).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert!(stack.is_empty());
+
+        // All nodes are on the free list now, so pushing again does not allocate.
+        for i in 0..100 {
+            stack.push(i);
+        }
+        assert!(stack.free.is_empty());
+    }
+}